@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::{statusline_timed, Options};
+use super::segments::Reason;
+
+/// The `p`th percentile of already sorted samples, by nearest rank.
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    return sorted[rank - 1];
+}
+
+/// Latency of one segment, or of the whole prompt, over a benchmark run.
+pub struct Summary {
+    pub name: String,
+    pub p50: Duration,
+    pub p95: Duration,
+}
+
+fn summarize(name: &str, mut samples: Vec<Duration>) -> Summary {
+    samples.sort();
+    return Summary{name: name.to_owned(), p50: percentile(&samples, 50), p95: percentile(&samples, 95)};
+}
+
+/// Render the prompt for the cwd `iterations` times, returning the latency
+/// of each segment, slowest first, followed by the total.
+pub fn run(options: &Options, iterations: usize) -> Vec<Summary> {
+    let mut order: Vec<String> = vec![];
+    let mut segments: HashMap<String, Vec<Duration>> = HashMap::new();
+    let mut totals = vec![];
+    for _ in 0..iterations {
+        let started = Instant::now();
+        let (_, timings) = statusline_timed(options);
+        totals.push(started.elapsed());
+        for timing in timings {
+            if timing.reason == Reason::Disabled {
+                continue;
+            }
+            if !segments.contains_key(&timing.name) {
+                order.push(timing.name.to_owned());
+            }
+            segments.entry(timing.name).or_default().push(timing.took);
+        }
+    }
+    let mut summaries: Vec<Summary> = order.iter()
+        .map(|name| summarize(name, segments.remove(name).unwrap_or_default()))
+        .collect();
+    summaries.sort_by_key(|summary| std::cmp::Reverse(summary.p95));
+    summaries.push(summarize("total", totals));
+    return summaries;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(&[], 50, 0)]
+    #[case(&[7], 95, 7)]
+    #[case(&[1, 2, 3, 4], 50, 2)]
+    #[case(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10], 50, 5)]
+    #[case(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10], 95, 10)]
+    fn test_percentile(#[case] millis: &[u64], #[case] p: usize, #[case] expected: u64) {
+        let sorted: Vec<Duration> = millis.iter().copied().map(Duration::from_millis).collect();
+        let actual = percentile(&sorted, p);
+        assert_eq!(Duration::from_millis(expected), actual)
+    }
+}
@@ -0,0 +1,230 @@
+use super::{config, GitSegment, PathSegment};
+use super::report::{SegmentReport, StatusReport};
+use super::segments::{plugin, Context, Registry, Segment, Timing};
+use super::style::{Color, Style};
+pub use super::style::Format;
+
+
+/// A composed statusline, ready to render for any directory.
+pub struct Statusline {
+    registry: Registry,
+    format: Format,
+    separator: String,
+}
+
+impl Statusline {
+    pub fn builder() -> StatuslineBuilder {
+        return StatuslineBuilder::default();
+    }
+
+    pub fn render(&self, ctx: &Context) -> String {
+        return self.render_timed(ctx).0;
+    }
+
+    /// Render, also returning how long each segment took.
+    pub fn render_timed(&self, ctx: &Context) -> (String, Vec<Timing>) {
+        let (report, timings) = self.collect(ctx);
+        return (report.render(self.format, &self.separator), timings);
+    }
+
+    /// Gather everything the statusline shows without drawing it.
+    pub fn collect(&self, ctx: &Context) -> (StatusReport, Vec<Timing>) {
+        let mut timings = vec![];
+        let mut segments = vec![];
+        for (timing, text) in self.registry.render_timed(ctx) {
+            if let Some(text) = text {
+                segments.push(SegmentReport{name: timing.name.to_owned(), text});
+            }
+            timings.push(timing);
+        }
+        let report = StatusReport{
+            path: ctx.path.to_string_lossy().into_owned(),
+            vcs: ctx.gathered_git().cloned(),
+            segments,
+        };
+        return (report, timings);
+    }
+}
+
+/// Picks the segments of a `Statusline` and how it's drawn, e.g.
+/// `Statusline::builder().path(2).git().exit_code().build()`.
+#[derive(Default)]
+pub struct StatuslineBuilder {
+    path: Option<PathSegment>,
+    segments: Registry,
+    format: Format,
+    separator: Option<String>,
+    order: Option<Vec<String>>,
+}
+
+impl StatuslineBuilder {
+    fn path_segment(&mut self) -> &mut PathSegment {
+        return self.path.get_or_insert_with(|| PathSegment{vcs: false, ..PathSegment::default()});
+    }
+
+    /// Start with the working directory, keeping the last `keep` names whole.
+    pub fn path(mut self, keep: usize) -> Self {
+        self.path_segment().keep = keep;
+        return self;
+    }
+
+    /// In a repo, keep the last `keep` names leading up to and including
+    /// its root whole, rather than just the root's.
+    pub fn outer_keep(mut self, keep: usize) -> Self {
+        self.path_segment().outer_keep = keep;
+        return self;
+    }
+
+    /// Draw the directory names in a color such as `cyan` or `214`.
+    pub fn path_color(mut self, name: &str) -> Self {
+        if let Some(color) = Color::parse(name) {
+            self.path_segment().style = Style::fg(color);
+        }
+        return self;
+    }
+
+    /// Splice the git branch and status into the path at the repo root.
+    pub fn git(mut self) -> Self {
+        self.path_segment().vcs = true;
+        return self;
+    }
+
+    /// End with the prompt character, coloured by the last exit status.
+    pub fn exit_code(self) -> Self {
+        return self.segment("character");
+    }
+
+    /// Add the builtin segment called `name`; unknown names are ignored.
+    pub fn segment(mut self, name: &str) -> Self {
+        if let Some(registry) = Registry::named(name) {
+            self.segments.extend(registry);
+        }
+        return self;
+    }
+
+    /// Add every builtin segment, in their default order.
+    pub fn builtins(mut self) -> Self {
+        self.segments.extend(Registry::builtin());
+        return self;
+    }
+
+    /// Add the `statusline-<name>` plugins found on PATH, if enabled.
+    pub fn plugins(mut self) -> Self {
+        self.segments.extend(plugin::discover());
+        return self;
+    }
+
+    /// Add a segment implemented outside this crate.
+    pub fn with(mut self, segment: impl Segment + 'static) -> Self {
+        self.segments.register(segment);
+        return self;
+    }
+
+    /// What goes between segments, a single space by default.
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = Some(separator.to_owned());
+        return self;
+    }
+
+    /// Arrange the segments by name, e.g. `["character", "git", "path", "*"]`,
+    /// where `*` is everything not listed. Naming `git` draws the VCS status
+    /// as a segment of its own instead of inside the path. Defaults to
+    /// `segments.order` from the config.
+    pub fn order(mut self, order: &[&str]) -> Self {
+        self.order = Some(order.iter().map(|name| name.to_string()).collect());
+        return self;
+    }
+
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        return self;
+    }
+
+    pub fn build(self) -> Statusline {
+        let order: Vec<String> = self.order.unwrap_or_else(|| {
+            return config::strings("segments.order").into_iter().map(str::to_owned).collect();
+        });
+        let order: Vec<&str> = order.iter().map(String::as_str).collect();
+        let mut registry = Registry::default();
+        if let Some(mut path) = self.path {
+            if path.vcs && order.contains(&"git") {
+                path.vcs = false;
+                registry.register(GitSegment);
+            }
+            registry.register(path);
+        }
+        registry.extend(self.segments);
+        registry.reorder(&order);
+        return Statusline{
+            registry,
+            format: self.format,
+            separator: self.separator.unwrap_or_else(|| " ".to_owned()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::segments::StyledText;
+    use crate::status::Options;
+    use rstest::rstest;
+
+    struct Fixed(&'static str);
+
+    impl Segment for Fixed {
+        fn name(&self) -> &str {
+            return "fixed";
+        }
+
+        fn render(&self, _ctx: &Context) -> Option<StyledText> {
+            return Some(StyledText::from_ansi(self.0));
+        }
+    }
+
+    struct Named(&'static str);
+
+    impl Segment for Named {
+        fn name(&self) -> &str {
+            return self.0;
+        }
+
+        fn render(&self, _ctx: &Context) -> Option<StyledText> {
+            return Some(StyledText::from_ansi(self.0));
+        }
+    }
+
+    #[rstest]
+    #[case(&[], "a b c")]
+    #[case(&["c", "a"], "c a b")]
+    #[case(&["c", "*", "a"], "c b a")]
+    #[case(&["missing", "b"], "b a c")]
+    fn test_order(#[case] order: &[&str], #[case] expected: &str) {
+        let statusline = Statusline::builder()
+            .with(Named("a"))
+            .with(Named("b"))
+            .with(Named("c"))
+            .order(order)
+            .build();
+        let ctx = Context::new("/".into(), Options::default());
+        let actual = statusline.render(&ctx);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case(Format::Ansi, " ", "\x1b[31mone\x1b[m two")]
+    #[case(Format::Plain, " ", "one two")]
+    #[case(Format::Plain, " | ", "one | two")]
+    #[case(Format::Tmux, " ", "#[fg=colour1]one#[default] two")]
+    fn test_render(#[case] format: Format, #[case] separator: &str, #[case] expected: &str) {
+        let statusline = Statusline::builder()
+            .with(Fixed("\x1b[31mone\x1b[m"))
+            .with(Fixed("two"))
+            .separator(separator)
+            .format(format)
+            .build();
+        let ctx = Context::new("/".into(), Options::default());
+        let actual = statusline.render(&ctx);
+        assert_eq!(expected, actual)
+    }
+}
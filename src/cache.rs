@@ -0,0 +1,123 @@
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Whether this is the statusline binary, which refreshes by running itself.
+static RESPAWN: AtomicBool = AtomicBool::new(false);
+
+fn cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CACHE_HOME") {
+        return Some(Path::new(&dir).join("statusline"));
+    }
+    return env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(|home| Path::new(&home).join(".cache/statusline"));
+}
+
+/// A short stable hash, so keys and secrets never appear in cache files verbatim.
+pub fn digest(value: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    return format!("{:016x}", hasher.finish());
+}
+
+/// Where the value for `key` is kept, for callers storing more than a string.
+pub fn cache_file(key: &str) -> Option<PathBuf> {
+    return Some(cache_dir()?.join(digest(key)));
+}
+
+/// Modification time of a file as a string usable as a cache fingerprint.
+pub fn mtime(path: &Path) -> Option<String> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let nanos = modified.duration_since(UNIX_EPOCH).ok()?.as_nanos();
+    return Some(nanos.to_string());
+}
+
+/// The fingerprint and value last stored for `key`.
+pub fn read(key: &str) -> Option<(String, String)> {
+    let contents = fs::read_to_string(cache_file(key)?).ok()?;
+    let (fingerprint, value) = contents.split_once('\n')?;
+    return Some((fingerprint.to_owned(), value.to_owned()));
+}
+
+pub fn write(key: &str, fingerprint: &str, value: &str) {
+    let Some(file) = cache_file(key) else {
+        return;
+    };
+    if let Some(dir) = file.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    // write then rename so a concurrent reader never sees half a value
+    let partial = file.with_extension(process::id().to_string());
+    if fs::write(&partial, format!("{fingerprint}\n{value}")).is_ok() {
+        let _ = fs::rename(partial, file);
+    }
+}
+
+/// Return the value stored for `key` if it was computed with the same `fingerprint`,
+/// otherwise compute it and store it for next time.
+pub fn get_or_insert(key: &str, fingerprint: &str, compute: impl FnOnce() -> Option<String>) -> Option<String> {
+    if let Some((stored, value)) = read(key) {
+        if stored == fingerprint {
+            return Some(value);
+        }
+    }
+    let value = compute()?;
+    write(key, fingerprint, &value);
+    return Some(value);
+}
+
+/// Seconds since the unix epoch, for time-based fingerprints.
+pub fn now() -> u64 {
+    return SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs());
+}
+
+/// Have `refresh_in_background` run this executable as `statusline
+/// __refresh <task>`, which only the statusline binary understands, so the
+/// work outlives a prompt that exits right away.
+pub fn refresh_by_respawning() {
+    RESPAWN.store(true, Ordering::Relaxed);
+}
+
+/// Do slow work behind the prompt so it lands in the cache for a later one
+/// instead of delaying this one: as a detached `statusline __refresh
+/// <task>` in the statusline binary, and on a thread of its own in a
+/// program embedding the crate.
+pub fn refresh_in_background(task: &str) {
+    if !RESPAWN.load(Ordering::Relaxed) {
+        let task = task.to_owned();
+        thread::spawn(move || crate::status::segments::refresh(&task));
+        return;
+    }
+    let Ok(exe) = env::current_exe() else {
+        return;
+    };
+    let _ = Command::new(exe)
+        .args(["__refresh", task])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+/// The value last stored for `key` by a background `task`, starting the task
+/// again when that value is older than `ttl` seconds or missing.
+pub fn refreshed(key: &str, ttl: u64, task: &str) -> Option<String> {
+    let (checked, value) = read(key).unwrap_or_default();
+    let checked: u64 = checked.parse().unwrap_or(0);
+    if now().saturating_sub(checked) > ttl {
+        // stamp the old value first so later prompts don't start another refresh
+        write(key, &now().to_string(), &value);
+        refresh_in_background(task);
+    }
+    return Some(value).filter(|value| !value.is_empty());
+}
+
+/// Store the result of a background task for `refreshed`.
+pub fn store_refreshed(key: &str, value: &str) {
+    write(key, &now().to_string(), value);
+}
@@ -0,0 +1,176 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
+use toml::{Table, Value};
+
+use crate::status::log;
+
+static CONFIG: OnceLock<Table> = OnceLock::new();
+
+/// Where the config is read from: `$STATUSLINE_CONFIG`, or `statusline/config.toml`
+/// in the XDG config directory.
+pub fn config_file() -> Option<PathBuf> {
+    if let Some(file) = env::var_os("STATUSLINE_CONFIG") {
+        return Some(PathBuf::from(file));
+    }
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        return Some(Path::new(&dir).join("statusline/config.toml"));
+    }
+    return env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(|home| Path::new(&home).join(".config/statusline/config.toml"));
+}
+
+fn load() -> Table {
+    let Some(file) = config_file() else {
+        return Table::new();
+    };
+    let Ok(contents) = fs::read_to_string(&file) else {
+        log::debug(|| format!("no config at {}", file.display()));
+        return Table::new();
+    };
+    return contents.parse::<Table>().map(apply_theme).unwrap_or_else(|error| {
+        log::debug(|| format!("ignoring invalid config {}: {error}", file.display()));
+        return Table::new();
+    });
+}
+
+/// Lay `overlay` over `base`, merging tables they both have key by key.
+fn merge(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            },
+        }
+    }
+}
+
+/// The settings in `[themes.<name>]` laid over the rest, for the theme
+/// `$STATUSLINE_THEME` names, as `--theme` sets it, or else `theme`.
+fn apply_theme(mut table: Table) -> Table {
+    let Some(name) = env::var("STATUSLINE_THEME").ok().or_else(|| Some(table.get("theme")?.as_str()?.to_owned())) else {
+        return table;
+    };
+    let Some(theme) = table.get("themes").and_then(|themes| themes.get(&name)?.as_table().cloned()) else {
+        log::debug(|| format!("no theme called {name}"));
+        return table;
+    };
+    merge(&mut table, theme);
+    return table;
+}
+
+/// The user's config file, loaded once and empty if missing or invalid.
+pub fn get() -> &'static Table {
+    return CONFIG.get_or_init(load);
+}
+
+/// Look up a dotted key such as `go.show_installed`.
+pub fn value(key: &str) -> Option<&'static Value> {
+    let mut parts = key.split('.');
+    let mut value = get().get(parts.next()?)?;
+    for part in parts {
+        value = value.get(part)?;
+    }
+    return Some(value);
+}
+
+pub fn bool(key: &str, default: bool) -> bool {
+    let value = value(key).and_then(Value::as_bool);
+    if value.is_none() {
+        log::debug(|| format!("config {key} unset, defaulting to {default}"));
+    }
+    return value.unwrap_or(default);
+}
+
+pub fn integer(key: &str, default: i64) -> i64 {
+    return value(key).and_then(Value::as_integer).unwrap_or(default);
+}
+
+/// A float, also accepting integers so `threshold = 1` works.
+pub fn float(key: &str, default: f64) -> f64 {
+    return value(key)
+        .and_then(|value| value.as_float().or(value.as_integer().map(|value| value as f64)))
+        .unwrap_or(default);
+}
+
+pub fn string(key: &str) -> Option<&'static str> {
+    return value(key).and_then(Value::as_str);
+}
+
+/// An array of strings, empty when unset.
+pub fn strings(key: &str) -> Vec<&'static str> {
+    return value(key).and_then(Value::as_array)
+        .map(|values| values.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+}
+
+/// Parse durations such as `500ms`, `10s`, `5m` or `1h`; bare numbers are seconds.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return None,
+    };
+    return Duration::try_from_secs_f64(seconds).ok();
+}
+
+/// A duration given as a string like `10s` or a number of seconds, or None
+/// for one that's negative or too long to be a `Duration`.
+pub fn duration(value: &Value) -> Option<Duration> {
+    if let Some(text) = value.as_str() {
+        return parse_duration(text);
+    }
+    let seconds = value.as_float().or(value.as_integer().map(|value| value as f64))?;
+    return Duration::try_from_secs_f64(seconds).ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[test]
+    fn test_merge() {
+        let mut base: Table = "theme = \"dark\"\n[path]\ncolor = \"blue\"\nkeep = 2\n".parse().unwrap();
+        let overlay: Table = "[path]\ncolor = \"cyan\"\n[git]\nnested = false\n".parse().unwrap();
+        merge(&mut base, overlay);
+        let expected: Table = "theme = \"dark\"\n[path]\ncolor = \"cyan\"\nkeep = 2\n[git]\nnested = false\n".parse().unwrap();
+        assert_eq!(expected, base)
+    }
+
+    #[rstest]
+    #[case("500ms", Some(Duration::from_millis(500)))]
+    #[case("10s", Some(Duration::from_secs(10)))]
+    #[case("1.5m", Some(Duration::from_secs(90)))]
+    #[case("2h", Some(Duration::from_secs(7200)))]
+    #[case("30", Some(Duration::from_secs(30)))]
+    #[case("soon", None)]
+    #[case("1000000000000000000000000s", None)]
+    #[case("inf", None)]
+    #[case("-5s", None)]
+    fn test_parse_duration(#[case] input: &str, #[case] expected: Option<Duration>) {
+        let actual = parse_duration(input);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case(Value::Integer(3), Some(Duration::from_secs(3)))]
+    #[case(Value::Float(0.25), Some(Duration::from_millis(250)))]
+    #[case(Value::String("2m".to_owned()), Some(Duration::from_secs(120)))]
+    #[case(Value::Float(1e300), None)]
+    #[case(Value::Float(f64::INFINITY), None)]
+    #[case(Value::Float(f64::NAN), None)]
+    #[case(Value::Integer(-1), None)]
+    fn test_duration(#[case] input: Value, #[case] expected: Option<Duration>) {
+        let actual = duration(&input);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+
+use super::{cache, config, git, log, render, segments, target, Options};
+#[cfg(feature = "watch")]
+use super::watch::Watcher;
+
+/// The variables segments read, and the tools they run, which are all of
+/// the client's environment the daemon is sent. The rest, tokens and all,
+/// stays with the client.
+const FORWARDED: &[&str] = &[
+    "HOME", "USERPROFILE", "PATH", "PWD", "USER", "SHELL", "SHLVL", "TERM", "LANG",
+    "TMUX", "STY", "SSH_AUTH_SOCK", "MAIL", "MSYSTEM", "WSL_DISTRO_NAME", "IN_NIX_SHELL",
+    "NO_COLOR", "CLICOLOR_FORCE", "STATUSLINE_CONFIG", "STATUSLINE_THEME", "STATUSLINE_LOG",
+    "AWS_PROFILE", "AWS_DEFAULT_PROFILE", "AWS_VAULT", "AWS_REGION", "AWS_DEFAULT_REGION", "AWS_CONFIG_FILE",
+    "VAULT_ADDR", "KUBECONFIG", "VIRTUAL_ENV", "JAVA_HOME", "DOTNET_ROOT", "RUBY_VERSION",
+    "GOPATH", "GOROOT", "GOTOOLCHAIN", "GOFLAGS",
+    "https_proxy", "HTTPS_PROXY", "http_proxy", "HTTP_PROXY", "all_proxy", "ALL_PROXY",
+];
+
+/// Families of variables forwarded whole: those of git, the toolchains and
+/// the base directories, none of which hold credentials.
+const FORWARDED_PREFIXES: &[&str] = &["XDG_", "LC_", "GIT_", "RUSTUP_", "CARGO_", "PYENV_", "RBENV_", "ASDF_", "POETRY_"];
+
+/// Whether the daemon is sent the variable `name`: one of `FORWARDED`, one
+/// a `[segment.env.*]` shows, one listed in `daemon.forward`, or
+/// `VAULT_TOKEN` when the vault segment is on and checks it.
+fn forwarded(name: &str) -> bool {
+    if FORWARDED.contains(&name) || FORWARDED_PREFIXES.iter().any(|prefix| name.starts_with(prefix)) {
+        return true;
+    }
+    if name == "VAULT_TOKEN" {
+        return config::bool("vault.enabled", false);
+    }
+    let shown = config::value("segment.env").and_then(toml::Value::as_table).is_some_and(|segments| {
+        return segments.iter().any(|(key, segment)| segment.get("variable").and_then(toml::Value::as_str).unwrap_or(key) == name);
+    });
+    return shown || config::strings("daemon.forward").contains(&name);
+}
+
+fn uid() -> u32 {
+    // SAFETY: getuid can't fail
+    return unsafe { libc::getuid() };
+}
+
+/// The user on the other end of a connection.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> Option<u32> {
+    let mut credentials = libc::ucred{pid: 0, uid: 0, gid: 0};
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    // SAFETY: the buffer and its length describe a ucred, as SO_PEERCRED fills in
+    let result = unsafe {
+        libc::getsockopt(stream.as_raw_fd(), libc::SOL_SOCKET, libc::SO_PEERCRED, (&raw mut credentials).cast(), &mut len)
+    };
+    return (result == 0).then_some(credentials.uid);
+}
+
+/// The user on the other end of a connection.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> Option<u32> {
+    let (mut uid, mut gid) = (0, 0);
+    // SAFETY: both pointers are to live values getpeereid writes to
+    let result = unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) };
+    return (result == 0).then_some(uid);
+}
+
+/// Whether `stream` is connected to a process of this same user, the only
+/// one a client sends its environment to or a daemon renders for.
+fn same_user(stream: &UnixStream) -> bool {
+    return peer_uid(stream) == Some(uid());
+}
+
+/// The socket's name, which tells apart the config file and theme the
+/// daemon loaded when it started, so that each has a daemon of its own.
+fn socket_name() -> String {
+    let file = config::config_file().unwrap_or_default();
+    let theme = env::var("STATUSLINE_THEME").unwrap_or_default();
+    return format!("statusline-{}.sock", cache::digest(&format!("{}\n{theme}", file.display())));
+}
+
+/// Where the daemon listens: in `$XDG_RUNTIME_DIR`, or else in a directory
+/// of /tmp only the user can get into. That directory is made if it's
+/// missing, and refused if someone else made it or can get in.
+pub fn socket_path() -> io::Result<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_RUNTIME_DIR") {
+        return Ok(Path::new(&dir).join(socket_name()));
+    }
+    let dir = PathBuf::from(format!("/tmp/statusline-{}", uid()));
+    match fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(error) if error.kind() != io::ErrorKind::AlreadyExists => return Err(error),
+        _ => {},
+    }
+    let metadata = fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid() || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{} isn't private to this user", dir.display())));
+    }
+    return Ok(dir.join(socket_name()));
+}
+
+/// A prompt request: the client's directory, environment and options.
+fn encode(path: &Path, env: &HashMap<String, String>, options: &Options) -> String {
+    return json!({
+        "path": path.to_string_lossy(),
+        "env": env,
+        "keymap": options.keymap,
+        "history": options.history,
+        "status": options.status,
+        "deferred": options.deferred,
+        "keep": options.keep,
+        "outer_keep": options.outer_keep,
+        "duration": options.duration.map(|duration| duration.as_secs_f64()),
+        "jobs": options.jobs,
+        "format": options.format,
+    }).to_string();
+}
+
+fn decode(request: &str) -> Option<(PathBuf, HashMap<String, String>, Options)> {
+    let request: Value = serde_json::from_str(request).ok()?;
+    let path = PathBuf::from(request["path"].as_str()?);
+    let env = request["env"].as_object()?.iter()
+        .filter_map(|(key, value)| Some((key.to_owned(), value.as_str()?.to_owned())))
+        .collect();
+    let options = Options{
+        keymap: request["keymap"].as_str().map(str::to_owned),
+        history: request["history"].as_u64(),
+        status: request["status"].as_i64().and_then(|status| i32::try_from(status).ok()),
+        deferred: request["deferred"].as_bool().unwrap_or(false),
+        keep: request["keep"].as_u64().and_then(|keep| usize::try_from(keep).ok()),
+        outer_keep: request["outer_keep"].as_u64().and_then(|keep| usize::try_from(keep).ok()),
+        duration: request["duration"].as_f64().and_then(|seconds| Duration::try_from_secs_f64(seconds).ok()),
+        jobs: request["jobs"].as_u64().and_then(|jobs| usize::try_from(jobs).ok()),
+        format: serde_json::from_value(request["format"].clone()).unwrap_or_default(),
+        // the path is sent on its own
+        path: None,
+    };
+    return Some((path, env, options));
+}
+
+/// Ask a running daemon for the prompt, or None if there isn't one answering.
+pub fn request(options: &Options) -> Option<String> {
+    let mut stream = UnixStream::connect(socket_path().ok()?).ok()?;
+    if !same_user(&stream) {
+        log::debug(|| "not asking the daemon: its socket belongs to another user".to_owned());
+        return None;
+    }
+    stream.set_read_timeout(Some(Duration::from_secs(1))).ok()?;
+    let path = target(options);
+    let env = env::vars().filter(|(name, _)| forwarded(name)).collect();
+    writeln!(stream, "{}", encode(&path, &env, options)).ok()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok()?;
+    if let Some(error) = response.strip_prefix(FAILED) {
+        log::debug(|| format!("the daemon couldn't render the prompt: {error}"));
+        return None;
+    }
+    return Some(response);
+}
+
+/// Starts a reply that's an error rather than a prompt, which a prompt
+/// can't start with.
+const FAILED: char = '\0';
+
+/// How long a request waits for segments still rendering from the last
+/// one to finish, before it's left to the client to render its own prompt.
+const SETTLE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Whether every segment has finished rendering, waiting up to
+/// `SETTLE_TIMEOUT` for those that missed their deadline.
+fn settled() -> bool {
+    let deadline = Instant::now() + SETTLE_TIMEOUT;
+    while segments::running() > 0 {
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+    return true;
+}
+
+/// What a panic was raised with, for the reply.
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    return panic.downcast_ref::<&str>().map(|message| (*message).to_owned())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "a segment panicked".to_owned());
+}
+
+/// How long a client gets to send its request and read the prompt before
+/// it's dropped, so a stalled one doesn't hold up everyone else's.
+const CLIENT_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// What git state a rendered prompt depends on, that of the enclosing
+/// repository, worktrees and submodules included.
+fn repo_fingerprint(root: Option<&Path>) -> String {
+    return root.map(|root| format!("{}:{}", root.display(), git::repo_fingerprint(root))).unwrap_or_default();
+}
+
+struct Entry {
+    rendered_at: Instant,
+    fingerprint: String,
+    prompt: String,
+}
+
+/// Whether a prompt rendered at `rendered_at` for a path in the repo at
+/// `root` predates the watcher's latest refresh of its status.
+#[cfg(feature = "watch")]
+fn outdated(watcher: Option<&Watcher>, root: Option<&Path>, rendered_at: Instant) -> bool {
+    let (Some(watcher), Some(root)) = (watcher, root) else {
+        return false;
+    };
+    return watcher.refreshed(root).is_some_and(|refreshed| refreshed > rendered_at);
+}
+
+#[cfg(not(feature = "watch"))]
+fn outdated(_watcher: Option<&()>, _root: Option<&Path>, _rendered_at: Instant) -> bool {
+    return false;
+}
+
+/// Serve prompts over the socket until killed. Renders run one at a time
+/// in the client's directory and environment, and are reused for identical
+/// requests until the repo's HEAD or index changes or `daemon.ttl` (2s by
+/// default) passes, which bounds how stale working-tree changes can be.
+/// Built with the `watch` feature, the repos prompts are asked for are
+/// watched too, their status gathered again in the background as soon as
+/// anything in them changes, so no request waits on `git status`.
+///
+/// The config is loaded once, so a daemon serves only the clients with the
+/// same config file and theme, which its socket is named for; run one for
+/// each. A render that panics is answered with an error, after which the
+/// client renders the prompt itself.
+///
+/// Segments read the environment directly, so it's swapped for the
+/// client's before each render. That's only sound while nothing but std,
+/// which serializes its reads with the swap, reads it meanwhile: the swap
+/// waits for every segment thread of the earlier renders to finish, and
+/// the watcher's thread only reads it through std, to run git.
+pub fn serve() -> std::io::Result<()> {
+    let socket = socket_path()?;
+    let _ = fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket)?;
+    let ttl = config::value("daemon.ttl").and_then(config::duration).unwrap_or(Duration::from_secs(2));
+    let mut entries: HashMap<String, Entry> = HashMap::new();
+    #[cfg(feature = "watch")]
+    let watcher = Watcher::new();
+    #[cfg(not(feature = "watch"))]
+    let watcher: Option<()> = None;
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        if !same_user(&stream) {
+            continue;
+        }
+        if stream.set_read_timeout(Some(CLIENT_TIMEOUT)).and_then(|_| stream.set_write_timeout(Some(CLIENT_TIMEOUT))).is_err() {
+            continue;
+        }
+        let mut request = String::new();
+        if BufReader::new(&stream).read_line(&mut request).is_err() {
+            continue;
+        }
+        let Some((path, client_env, options)) = decode(&request) else {
+            continue;
+        };
+        let key = cache::digest(request.trim_end());
+        let root = git::find_root(&path);
+        let fingerprint = repo_fingerprint(root);
+        #[cfg(feature = "watch")]
+        if let (Some(watcher), Some(root)) = (&watcher, root) {
+            watcher.watch(root);
+        }
+        let fresh = entries.get(&key).is_some_and(|entry| {
+            return entry.fingerprint == fingerprint && entry.rendered_at.elapsed() < ttl
+                && !outdated(watcher.as_ref(), root, entry.rendered_at);
+        });
+        if !fresh {
+            if !settled() {
+                let _ = write!(stream, "{FAILED}segments of an earlier prompt are still rendering");
+                continue;
+            }
+            if env::set_current_dir(&path).is_err() {
+                continue;
+            }
+            // segments read the environment directly, so take on the client's
+            for (name, _) in env::vars_os() {
+                env::remove_var(name);
+            }
+            for (name, value) in client_env {
+                env::set_var(name, value);
+            }
+            let prompt = match panic::catch_unwind(AssertUnwindSafe(|| render(path, &options))) {
+                Ok(prompt) => prompt,
+                Err(panic) => {
+                    let _ = write!(stream, "{FAILED}{}", panic_message(panic.as_ref()));
+                    continue;
+                },
+            };
+            entries.insert(key.clone(), Entry{rendered_at: Instant::now(), fingerprint, prompt});
+        }
+        let _ = stream.write_all(entries[&key].prompt.as_bytes());
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use crate::status::style::Format;
+
+    #[rstest]
+    #[case(Options::default())]
+    #[case(Options{keymap: Some("vicmd".to_owned()), history: Some(42), status: Some(-1), deferred: true, ..Options::default()})]
+    #[case(Options{keep: Some(2), outer_keep: Some(3), duration: Some(Duration::from_millis(1500)), jobs: Some(3), format: Format::Tmux, ..Options::default()})]
+    fn test_round_trip(#[case] options: Options) {
+        let env = HashMap::from([("AWS_PROFILE".to_owned(), "prod".to_owned())]);
+        let (path, actual_env, actual) = decode(&encode(Path::new("/srv/repo"), &env, &options)).unwrap();
+        assert_eq!(PathBuf::from("/srv/repo"), path);
+        assert_eq!(env, actual_env);
+        assert_eq!(options, actual)
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let actual = panic::catch_unwind(|| panic!("no {}", "segment")).map_err(|panic| panic_message(panic.as_ref()));
+        assert_eq!(Err("no segment".to_owned()), actual)
+    }
+
+    #[rstest]
+    #[case("AWS_PROFILE", true)]
+    #[case("GIT_DIR", true)]
+    #[case("XDG_CONFIG_HOME", true)]
+    #[case("AWS_SECRET_ACCESS_KEY", false)]
+    #[case("GITHUB_TOKEN", false)]
+    #[case("GOOGLE_API_KEY", false)]
+    #[case("VAULT_TOKEN", false)]
+    fn test_forwarded(#[case] name: &str, #[case] expected: bool) {
+        let actual = forwarded(name);
+        assert_eq!(expected, actual)
+    }
+}
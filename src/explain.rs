@@ -0,0 +1,57 @@
+use std::fmt::Write;
+
+use super::report::StatusReport;
+use super::segments::{Reason, Timing};
+
+/// A line per segment saying why it was shown or hidden, followed by the
+/// text it drew and the style of each piece, then the repository data the
+/// path was built from. For `--explain`, to see which segment or setting
+/// is responsible for what ends up in the prompt.
+pub fn explain(report: &StatusReport, timings: &[Timing]) -> String {
+    let mut result = String::new();
+    let width = timings.iter().map(|timing| timing.name.len()).max().unwrap_or(0);
+    let mut shown = report.segments.iter();
+    for timing in timings {
+        let _ = writeln!(result, "{:<width$}  {} ({:.1?})", timing.name, timing.reason, timing.took);
+        if !timing.shown {
+            continue;
+        }
+        let Some(segment) = shown.next() else {
+            continue;
+        };
+        if timing.reason == Reason::TimedOut {
+            let _ = writeln!(result, "{:<width$}    drawn as segments.timeout_placeholder", "");
+        }
+        for span in &segment.text.spans {
+            let _ = writeln!(result, "{:<width$}    {:?} {}", "", span.text, span.style);
+        }
+    }
+    if let Some(vcs) = &report.vcs {
+        let _ = writeln!(result, "repository: {}", serde_json::to_string(vcs).unwrap_or_default());
+    }
+    return result;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::status::report::SegmentReport;
+    use crate::status::style::{Color, Style, StyledText};
+
+    #[test]
+    fn test_explain() {
+        let report = StatusReport{
+            path: "/srv".to_owned(),
+            vcs: None,
+            segments: vec![SegmentReport{name: "path".to_owned(), text: StyledText::new().push(Style::fg(Color::RED).bold(), "/srv")}],
+        };
+        let timings = vec![
+            Timing{name: "path".to_owned(), took: Duration::ZERO, shown: true, reason: Reason::Shown},
+            Timing{name: "kube".to_owned(), took: Duration::ZERO, shown: false, reason: Reason::Disabled},
+        ];
+        let expected = "path  shown (0.0ns)\n        \"/srv\" bold red\nkube  listed in segments.disabled (0.0ns)\n";
+        let actual = explain(&report, &timings);
+        assert_eq!(expected, actual)
+    }
+}
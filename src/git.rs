@@ -1,5 +1,19 @@
-use std::process::Command;
+use std::collections::HashMap;
+use std::env;
 use std::fmt;
+use std::fs;
+use std::io;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+
+use crate::status::{cache, config, log};
+use crate::status::segments::{installed, output, run_command_in};
+use crate::status::style::{Color, Style, RESET};
 
 struct AheadBehind {
     ahead: usize,
@@ -43,40 +57,94 @@ impl fmt::Display for Status {
         }
 
         if self.staged > 0 {
-            write!(f, "\x1b[32m{}", self.staged)?;
+            write!(f, "{}{}", Style::fg(Color::GREEN).prefix(), self.staged)?;
         }
         if self.unstaged > 0 {
-            write!(f, "\x1b[31m{}", self.unstaged)?;
+            write!(f, "{}{}", Style::fg(Color::RED).prefix(), self.unstaged)?;
         }
         if self.untracked > 0 {
-            write!(f, "\x1b[90m{}", self.untracked)?;
+            write!(f, "{}{}", Style::fg(Color::GRAY).prefix(), self.untracked)?;
         }
-        return write!(f, "\x1b[m");
+        return write!(f, "{RESET}");
+    }
+}
+
+/// Why the VCS couldn't be queried.
+#[derive(Debug)]
+pub enum Error {
+    /// The VCS binary couldn't be run at all, e.g. it isn't installed.
+    Spawn(io::Error),
+    /// It ran but failed, typically because the cwd isn't in a repository.
+    Command(String),
+    /// The cwd isn't below the root the VCS reported.
+    Outside,
+    /// The VCS isn't on PATH, as in minimal containers.
+    NotInstalled,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Error::Spawn(error) => write!(f, "failed to run git: {error}"),
+            Error::Command(stderr) => write!(f, "git failed: {stderr}"),
+            Error::Outside => write!(f, "path is outside the repository root"),
+            Error::NotInstalled => write!(f, "git is not installed"),
+        };
     }
 }
 
+pub type Result<T> = std::result::Result<T, Error>;
+
 pub trait VCS {
-	fn root_dir(&self) -> String;
-	fn branch(&self) -> String;
-	fn stat(&self) -> String;
+	fn root_dir(&self) -> Result<String>;
+	fn branch(&self) -> Result<String>;
+	fn stat(&self) -> Result<String>;
 }
 
-pub struct Git;
+/// Git, run in `dir` or else the cwd.
+#[derive(Default)]
+pub struct Git {
+    pub dir: Option<PathBuf>,
+    /// The root once it's known, as `apply_vcs` asks for it before `stat`
+    /// does again.
+    root: OnceLock<String>,
+}
 
-const ICON: &str = "\x1b[38;5;202m\u{E0A0}\x1b[m";
+const ICON: &str = "\u{E0A0}";
+
+/// The result of a command run on its own thread, passing on its panic.
+fn joined<T>(handle: thread::ScopedJoinHandle<'_, T>) -> T {
+    return handle.join().unwrap_or_else(|panic| panic::resume_unwind(panic));
+}
 
 impl Git {
-    fn run_command(args: &[&str]) -> String {
-        // let args = ["rev-parse", "--symbolic-full-name", "--abbrev-ref", "HEAD"];
-        let output = Command::new("git")
-            .args(args)
-            .output()
-            .expect("failed to execute process");
-        return String::from_utf8(output.stdout).unwrap().trim_end().to_string();
+    pub fn new(dir: &Path) -> Git {
+        return Git{dir: Some(dir.to_path_buf()), ..Git::default()};
     }
 
-    fn count(args: &[&str]) -> usize {
-        let string = Git::run_command(args);
+    fn run_command(&self, args: &[&str]) -> Result<String> {
+        if !installed("git") {
+            return Err(Error::NotInstalled);
+        }
+        let started = Instant::now();
+        let mut command = Command::new("git");
+        if let Some(dir) = &self.dir {
+            command.current_dir(dir);
+        }
+        command.args(args);
+        let output = output(command);
+        log::command("git", args, started.elapsed(), output.as_ref().is_ok_and(|output| output.status.success()));
+        let output = output.map_err(Error::Spawn)?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(Error::Command(stderr.trim_end().to_owned()));
+        }
+        return Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string());
+    }
+
+    /// Lines of output, or 0 when the command fails, e.g. without an upstream.
+    fn count(&self, args: &[&str]) -> usize {
+        let string = self.run_command(args).unwrap_or_default();
         let mut output: Vec<&str> = string.split("\n").collect();
         if output.last() == Some(&"") {
             output.pop();
@@ -84,21 +152,25 @@ impl Git {
         return output.len();
     }
 
-    fn ahead_behind() -> AheadBehind {
-        return AheadBehind{
-            ahead: Git::count(&["rev-list", "@{push}..HEAD"]),
-            behind: Git::count(&["rev-list", "HEAD..@{upstream}"]),
+    fn ahead_behind(&self) -> AheadBehind {
+        if self.in_sync() == Some(true) {
+            return AheadBehind{ahead: 0, behind: 0};
         }
+        return thread::scope(|scope| {
+            let ahead = scope.spawn(|| self.count(&["rev-list", "@{push}..HEAD"]));
+            let behind = self.count(&["rev-list", "HEAD..@{upstream}"]);
+            return AheadBehind{ahead: joined(ahead), behind};
+        });
     }
 
-    fn status() -> Status {
+    fn status(&self) -> Result<Status> {
         let mut result = Status{
             staged: 0,
             unstaged: 0,
             untracked: 0,
         };
-        for line in Git::run_command(&["status", "--porcelain"]).split("\n") {
-            if line == "" {
+        for line in self.run_command(&["status", "--porcelain"])?.split("\n") {
+            if line.len() < 2 {
                 continue;
             }
             if str::starts_with(line, "??") {
@@ -112,39 +184,720 @@ impl Git {
                 }
             }
         }
-        return result
+        return Ok(result)
+    }
+
+    /// The work tree's git directory, found on disk for the usual
+    /// layouts, or None when `$GIT_DIR` or the like point elsewhere.
+    fn own_git_dir(&self) -> Option<PathBuf> {
+        if env::var_os("GIT_DIR").is_some() || env::var_os("GIT_COMMON_DIR").is_some() {
+            return None;
+        }
+        let dir = self.dir.clone().or_else(|| env::current_dir().ok())?;
+        return git_dir(find_root(&dir)?);
+    }
+
+    /// The git directory holding the refs, shared by linked worktrees.
+    fn common_dir(&self) -> Option<PathBuf> {
+        let dir = self.own_git_dir()?;
+        return Some(linked_common_dir(&dir).unwrap_or(dir));
+    }
+
+    fn read_head(&self) -> Option<String> {
+        return fs::read_to_string(self.own_git_dir()?.join("HEAD")).ok();
+    }
+
+    /// Whether HEAD is where its upstream is, worked out from the files
+    /// when the branch pushes to its upstream, so that neither side needs
+    /// counting. None when that can't be told without git.
+    fn in_sync(&self) -> Option<bool> {
+        let head = self.read_head()?;
+        let branch = head_ref(&head)?;
+        let common = self.common_dir()?;
+        // `push.default` and `remote.pushDefault` are often set for the user
+        // rather than the repo, which git would then have to be asked about
+        if pushes_elsewhere(&outer_config()) {
+            return None;
+        }
+        let config = fs::read_to_string(common.join("config")).ok()?;
+        let upstream = upstream_ref(&config, branch.strip_prefix("refs/heads/")?)?;
+        return Some(resolve_ref(&common, branch)? == resolve_ref(&common, &upstream)?);
     }
 
-    fn stashes() -> usize {
-        return Git::count(&["stash", "list"])
+    /// Each stash is an entry in the `refs/stash` reflog, so they're
+    /// counted from that rather than by running `git stash list`.
+    fn stashes(&self) -> usize {
+        let Some(common) = self.common_dir() else {
+            return self.logged_stashes();
+        };
+        if let Ok(log) = fs::read_to_string(common.join("logs/refs/stash")) {
+            return reflog_entries(&log);
+        }
+        let packed = fs::read_to_string(common.join("packed-refs")).unwrap_or_default();
+        if !common.join("refs/stash").exists() && !packed.lines().any(|line| line.ends_with(" refs/stash")) {
+            return 0;
+        }
+        // the ref is there without its log, which only git can make sense of
+        return self.logged_stashes();
+    }
+
+    fn logged_stashes(&self) -> usize {
+        return self.run_command(&["rev-list", "--walk-reflogs", "--count", "refs/stash"])
+            .ok().and_then(|count| count.parse().ok()).unwrap_or(0);
+    }
+
+    /// Everything the statusline shows about the repo, in one go.
+    pub fn summary(&self) -> Result<Summary> {
+        // outside a repo this fails before the slower commands run
+        let root = self.root_dir()?;
+        // the rest don't depend on each other, so the slowest sets the pace
+        return thread::scope(|scope| {
+            let branch = scope.spawn(|| self.branch());
+            let ab = scope.spawn(|| self.ahead_behind());
+            let stashes = scope.spawn(|| self.stashes());
+            let status = self.status()?;
+            let branch = joined(branch)?;
+            let ab = joined(ab);
+            return Ok(Summary{
+                root,
+                branch,
+                ahead: ab.ahead,
+                behind: ab.behind,
+                staged: status.staged,
+                unstaged: status.unstaged,
+                untracked: status.untracked,
+                stashes: joined(stashes),
+                stale: false,
+            });
+        });
     }
 }
 
 impl VCS for Git {
-    fn root_dir(&self) -> String {
-        return Git::run_command(&["rev-parse", "--show-toplevel"]);
+    fn root_dir(&self) -> Result<String> {
+        if let Some(root) = self.root.get() {
+            return Ok(root.to_owned());
+        }
+        let root = self.run_command(&["rev-parse", "--show-toplevel"])?;
+        return Ok(self.root.get_or_init(|| root).to_owned());
+    }
+
+    fn branch(&self) -> Result<String> {
+        if let Some(branch) = self.read_head().as_deref().and_then(head_branch) {
+            return Ok(branch.to_owned());
+        }
+        return self.run_command(&["rev-parse", "--symbolic-full-name", "--abbrev-ref", "HEAD"]);
     }
 
-    fn branch(&self) -> String {
-        return Git::run_command(&["rev-parse", "--symbolic-full-name", "--abbrev-ref", "HEAD"]);
+    fn stat(&self) -> Result<String> {
+        return self.summary()?.stat();
     }
+}
 
-    fn stat(&self) -> String {
-        let mut result = ICON.to_owned();
-        let branch = &self.branch();
-        if !str::ends_with(&self.root_dir(), branch) {
-            result += branch;
+/// The state of a repository as gathered from git, kept apart from how
+/// it's drawn so it can be cached and reported as data.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub root: String,
+    pub branch: String,
+    pub ahead: usize,
+    pub behind: usize,
+    pub staged: usize,
+    pub unstaged: usize,
+    pub untracked: usize,
+    pub stashes: usize,
+    /// This came from the cache, so changes since it was gathered, to
+    /// HEAD, the index or the files themselves, may be missing from it.
+    #[serde(default)]
+    pub stale: bool,
+}
+
+impl VCS for Summary {
+    fn root_dir(&self) -> Result<String> {
+        return Ok(self.root.to_owned());
+    }
+
+    fn branch(&self) -> Result<String> {
+        return Ok(self.branch.to_owned());
+    }
+
+    /// Spliced into the path, the branch is left out when the repo is named
+    /// after it, as the name is right before it.
+    fn stat(&self) -> Result<String> {
+        return Ok(self.decoration(!str::ends_with(&self.root, &self.branch)));
+    }
+}
+
+/// The state of a repository as a script checking on it sees it, told
+/// apart by `statusline --quiet`'s exit code.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum State {
+    Clean,
+    /// Anything `git status` lists, untracked files included.
+    Dirty,
+    /// Nothing changed, but commits ahead of or behind the upstream.
+    Diverged,
+    /// Not in a repository, or git couldn't say.
+    Outside,
+}
+
+impl State {
+    /// 0 clean, 1 dirty, 2 diverged, 3 not a repo.
+    pub fn code(self) -> i32 {
+        return match self {
+            State::Clean => 0,
+            State::Dirty => 1,
+            State::Diverged => 2,
+            State::Outside => 3,
+        };
+    }
+}
+
+impl Summary {
+    /// Changes to the work tree are reported before commits to push or pull.
+    pub fn state(&self) -> State {
+        if self.staged + self.unstaged + self.untracked > 0 {
+            return State::Dirty;
+        }
+        if self.ahead + self.behind > 0 {
+            return State::Diverged;
         }
-        let ab = Git::ahead_behind();
+        return State::Clean;
+    }
+
+    /// The icon, the branch if `branch` is set, then how far it is from its
+    /// upstream, the changes, the stashes and whether this is out of date.
+    pub fn decoration(&self, branch: bool) -> String {
+        let mut result = Style::fg(Color::Fixed(202)).paint(ICON);
+        if branch {
+            result += &self.branch;
+        }
+        let ab = AheadBehind{ahead: self.ahead, behind: self.behind};
         result += &format!("{ab}");
-        let status = Git::status();
+        let status = Status{staged: self.staged, unstaged: self.unstaged, untracked: self.untracked};
         if status.has_changes() {
             result += &format!("({status})");
         }
-        let stashes = Git::stashes();
-        if stashes > 0 {
-            result += &format!("{{{stashes}}}");
+        if self.stashes > 0 {
+            result += &format!("{{{}}}", self.stashes);
+        }
+        if self.stale {
+            result += &Style::fg(Color::GRAY).paint(STALE_MARKER);
         }
         return result;
     }
 }
+
+/// How long a cached git status is trusted before it's refreshed behind the prompt.
+const STALE_TTL: u64 = 5;
+const STALE_MARKER: &str = "~";
+
+/// The repository containing `path`, found without running git.
+pub fn find_root(path: &Path) -> Option<&Path> {
+    return path.ancestors().find(|dir| dir.join(".git").exists());
+}
+
+/// The git directory of a work tree, following the `gitdir:` pointer a
+/// worktree or submodule has in place of a `.git` directory.
+fn git_dir(root: &Path) -> Option<PathBuf> {
+    let dot_git = root.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let pointer = fs::read_to_string(&dot_git).ok()?;
+    let dir = pointer.strip_prefix("gitdir:")?.trim();
+    return Some(root.join(dir));
+}
+
+/// The names of the main repository and of the work tree at `root` when
+/// it's a linked worktree, like `("app", "fix-123")` for one made by
+/// `git worktree add`. Submodules have a `gitdir:` pointer too, but no
+/// `commondir` leading back to another repository.
+pub fn worktree(root: &Path) -> Option<(String, String)> {
+    let dir = git_dir(root)?;
+    let common = fs::canonicalize(linked_common_dir(&dir)?).ok()?;
+    return worktree_names(&common, root);
+}
+
+/// The git directory a linked worktree shares its refs with.
+fn linked_common_dir(dir: &Path) -> Option<PathBuf> {
+    let common = fs::read_to_string(dir.join("commondir")).ok()?;
+    return Some(dir.join(common.trim()));
+}
+
+/// The main repository's name from its git directory, either the `.git`
+/// inside its work tree or a bare `app.git`, and the worktree's own name.
+fn worktree_names(common: &Path, root: &Path) -> Option<(String, String)> {
+    let main = match common.file_name()?.to_str()? {
+        ".git" => common.parent()?.file_name()?.to_string_lossy().into_owned(),
+        bare => bare.strip_suffix(".git").unwrap_or(bare).to_owned(),
+    };
+    return Some((main, root.file_name()?.to_string_lossy().into_owned()));
+}
+
+/// Files that come and go with a merge, rebase, cherry-pick or revert.
+const STATE_FILES: &[&str] = &["MERGE_HEAD", "REBASE_HEAD", "CHERRY_PICK_HEAD", "REVERT_HEAD", "rebase-merge", "rebase-apply"];
+
+fn reflog_entries(log: &str) -> usize {
+    return log.lines().filter(|line| !line.trim().is_empty()).count();
+}
+
+/// The branch a `HEAD` file is on, or `HEAD` when it's detached, as
+/// `rev-parse --abbrev-ref` says; None for anything less usual.
+fn head_branch(head: &str) -> Option<&str> {
+    if let Some(name) = head_ref(head) {
+        return name.strip_prefix("refs/heads/");
+    }
+    let commit = head.trim();
+    if matches!(commit.len(), 40 | 64) && commit.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Some("HEAD");
+    }
+    return None;
+}
+
+/// The commit a full ref like `refs/heads/main` points to, from its own
+/// file or else `packed-refs`.
+fn resolve_ref(common: &Path, name: &str) -> Option<String> {
+    if let Ok(commit) = fs::read_to_string(common.join(name)) {
+        // a symbolic ref is left to git
+        return Some(commit.trim().to_owned()).filter(|commit| !commit.starts_with("ref:"));
+    }
+    let packed = fs::read_to_string(common.join("packed-refs")).ok()?;
+    return packed.lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|&(_, packed_name)| packed_name == name)
+        .map(|(commit, _)| commit.to_owned());
+}
+
+/// Whether anything in `config` could make a branch push somewhere other
+/// than its upstream, or pull in more settings that might.
+fn pushes_elsewhere(config: &str) -> bool {
+    let lower = config.to_ascii_lowercase();
+    return ["pushremote", "pushdefault", "[push", "[include"].iter().any(|setting| lower.contains(setting));
+}
+
+/// The config git reads besides the repo's own: the system's, then the
+/// user's in `$XDG_CONFIG_HOME/git/config` and `~/.gitconfig`, with any
+/// set through the environment standing in for the lot, as git can't be
+/// second-guessed about those.
+fn outer_config() -> String {
+    if env::var_os("GIT_CONFIG_PARAMETERS").is_some() || env::var_os("GIT_CONFIG_COUNT").is_some() {
+        return "[include]".to_owned();
+    }
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(PathBuf::from);
+    let mut files = vec![];
+    if env::var_os("GIT_CONFIG_NOSYSTEM").is_none() {
+        files.push(env::var_os("GIT_CONFIG_SYSTEM").map_or_else(|| PathBuf::from("/etc/gitconfig"), PathBuf::from));
+    }
+    match env::var_os("GIT_CONFIG_GLOBAL") {
+        Some(global) => files.push(PathBuf::from(global)),
+        None => {
+            let xdg = env::var_os("XDG_CONFIG_HOME").map(PathBuf::from).or_else(|| home.as_ref().map(|home| home.join(".config")));
+            files.extend(xdg.map(|dir| dir.join("git/config")));
+            files.extend(home.map(|home| home.join(".gitconfig")));
+        },
+    }
+    return files.iter().filter_map(|file| fs::read_to_string(file).ok()).collect::<Vec<String>>().join("\n");
+}
+
+/// The remote-tracking ref `branch` merges from, like
+/// `refs/remotes/origin/main`, as long as nothing in `config` could make
+/// it push elsewhere or pull in more settings.
+fn upstream_ref(config: &str, branch: &str) -> Option<String> {
+    if pushes_elsewhere(config) {
+        return None;
+    }
+    let header = format!("[branch \"{branch}\"]");
+    let (mut remote, mut merge) = (None, None);
+    let mut in_branch = false;
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_branch = line == header;
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').filter(|_| in_branch) else {
+            continue;
+        };
+        match key.trim().to_ascii_lowercase().as_str() {
+            "remote" => remote = Some(value.trim()),
+            "merge" => merge = Some(value.trim()),
+            _ => {},
+        }
+    }
+    let merge = merge?;
+    return match remote? {
+        "." => Some(merge.to_owned()),
+        remote => Some(format!("refs/remotes/{remote}/{}", merge.strip_prefix("refs/heads/")?)),
+    };
+}
+
+/// Add the settings in a git config file to `settings` by `section.key`,
+/// lowercased but for any subsection, as `git config --list` names them.
+/// A key without a value is a true boolean.
+fn parse_config(config: &str, settings: &mut HashMap<String, String>) {
+    let mut section = String::new();
+    for line in config.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.split(']').next().unwrap_or_default();
+            section = match header.split_once(char::is_whitespace) {
+                Some((name, sub)) => format!("{}.{}", name.to_ascii_lowercase(), sub.trim().trim_matches('"')),
+                None => header.to_ascii_lowercase(),
+            };
+            continue;
+        }
+        let (key, value) = line.split_once('=').unwrap_or((line, "true"));
+        settings.insert(format!("{section}.{}", key.trim().to_ascii_lowercase()), config_value(value));
+    }
+}
+
+/// A config value without its comment, quotes and escapes.
+fn config_value(raw: &str) -> String {
+    let mut value = String::new();
+    let mut quoted = false;
+    let mut chars = raw.trim().chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' | ';' if !quoted => break,
+            '\\' => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some(escaped) => value.push(escaped),
+                None => {},
+            },
+            c => value.push(c),
+        }
+    }
+    return value.trim().to_owned();
+}
+
+/// The settings from `git config --list -z`, each a key and its value on
+/// separate lines.
+fn parse_config_list(list: &str) -> HashMap<String, String> {
+    return list.split('\0')
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (key, value) = entry.split_once('\n').unwrap_or((entry, "true"));
+            return (key.to_owned(), value.to_owned());
+        })
+        .collect();
+}
+
+/// The ref a `HEAD` file points to, like `refs/heads/main`, or None when
+/// it's detached.
+fn head_ref(head: &str) -> Option<&str> {
+    return head.strip_prefix("ref:").map(str::trim);
+}
+
+/// Changes whenever a commit, checkout, `git add`, fetch into the current
+/// branch, or a merge or rebase starting or stopping happens.
+pub fn repo_fingerprint(root: &Path) -> String {
+    let Some(dir) = git_dir(root) else {
+        return String::new();
+    };
+    let common = linked_common_dir(&dir).unwrap_or_else(|| dir.clone());
+    let mtime = |path: PathBuf| cache::mtime(&path).unwrap_or_default();
+    let head = fs::read_to_string(dir.join("HEAD")).unwrap_or_default();
+    // a commit moves the branch HEAD points to rather than HEAD itself
+    let branch = head_ref(&head).map(|branch| mtime(common.join(branch))).unwrap_or_default();
+    let packed = mtime(common.join("packed-refs"));
+    let index = mtime(dir.join("index"));
+    let state: Vec<String> = STATE_FILES.iter().map(|file| mtime(dir.join(file))).collect();
+    return format!("{}:{branch}:{packed}:{index}:{}", head.trim(), state.join(","));
+}
+
+fn cache_key(root: &Path) -> String {
+    return format!("git:{}", root.display());
+}
+
+/// Compute the status for the repo at `root` and cache it with its fingerprint.
+fn store(root: &Path) -> Option<Summary> {
+    let fresh = Git::new(root).summary().ok()?;
+    let value = serde_json::to_string(&fresh).ok()?;
+    cache::write(&cache_key(root), &format!("{}@{}", repo_fingerprint(root), cache::now()), &value);
+    return Some(fresh);
+}
+
+/// Background half of `cached`, run as `__refresh git:<root>`.
+pub fn refresh(root: &str) {
+    store(Path::new(root));
+}
+
+/// Bring the cached status of the repo containing `path` up to date ahead of
+/// the prompt, run as `__refresh cd:<dir>` from the shell's hook for changing
+/// directory. An entry that still matches the repo is left alone.
+pub fn precompute(path: &Path) {
+    if !config::bool("git.stale_cache", true) {
+        return;
+    }
+    let Some(root) = find_root(path) else {
+        return;
+    };
+    let current = cache::read(&cache_key(root))
+        .and_then(|(stamp, _)| Some(stamp.rsplit_once('@')?.0 == repo_fingerprint(root)))
+        .unwrap_or(false);
+    if !current {
+        store(root);
+    }
+}
+
+/// The cached status of the repo containing `path`, computed on the spot
+/// only the first time. Anything from the cache is marked with a `~`, as
+/// edits to tracked files can't be told from the fingerprint; it's
+/// gathered again behind the prompt once HEAD, the index or a merge or
+/// rebase have moved on, or every few seconds, for the next prompt.
+/// `git.stale_cache = false` turns this off.
+pub fn cached(path: &Path) -> Option<Summary> {
+    if !config::bool("git.stale_cache", true) {
+        return None;
+    }
+    let root = find_root(path)?;
+    let key = cache_key(root);
+    let Some((stamp, value)) = cache::read(&key) else {
+        return store(root);
+    };
+    // entries written before the cache held JSON are simply recomputed
+    let Ok(mut summary) = serde_json::from_str::<Summary>(&value) else {
+        return store(root);
+    };
+    let (fingerprint, checked) = stamp.rsplit_once('@')?;
+    let checked: u64 = checked.parse().unwrap_or(0);
+    if fingerprint != repo_fingerprint(root) || cache::now().saturating_sub(checked) > STALE_TTL {
+        // stamp the old value so the next prompts don't start another refresh
+        cache::write(&key, &format!("{fingerprint}@{}", cache::now()), &value);
+        cache::refresh_in_background(&key);
+    }
+    summary.stale = true;
+    return Some(summary);
+}
+
+/// The status of the repo containing `path`, from the cache when it's enabled.
+pub fn summary_at(path: &Path) -> Option<Summary> {
+    return cached(path).or_else(|| Git::new(path).summary().ok());
+}
+
+/// The repos that the one at `root` is nested in, like the superproject
+/// of a submodule, outermost first, when `git.nested` asks for them. Each
+/// costs another status, and with dotfiles kept in a repo at `~` that's a
+/// status of the whole home directory, so they're left out by default.
+pub fn enclosing(root: &Path) -> Vec<Summary> {
+    let mut result = vec![];
+    if !config::bool("git.nested", false) {
+        return result;
+    }
+    let mut inner = root.to_path_buf();
+    // no `.git` above means there's no need to ask git
+    while let Some(parent) = inner.parent().filter(|parent| find_root(parent).is_some()) {
+        let Some(outer) = summary_at(parent) else {
+            break;
+        };
+        let outer_root = PathBuf::from(&outer.root);
+        // a root that isn't further up would never end
+        if outer_root.components().count() >= inner.components().count() {
+            break;
+        }
+        inner = outer_root;
+        result.push(outer);
+    }
+    result.reverse();
+    return result;
+}
+
+/// The repository a render is for, with each thing about it looked up at
+/// most once however many segments ask: its status, the repos it's nested
+/// in, and the output of any other git command run in it.
+pub struct GitRepo {
+    path: PathBuf,
+    summary: OnceLock<Option<Summary>>,
+    enclosing: OnceLock<Vec<Summary>>,
+    config: OnceLock<HashMap<String, String>>,
+    outputs: Mutex<HashMap<Vec<String>, Option<String>>>,
+}
+
+impl GitRepo {
+    pub fn new(path: &Path) -> GitRepo {
+        return GitRepo{
+            path: path.to_path_buf(),
+            summary: OnceLock::new(),
+            enclosing: OnceLock::new(),
+            config: OnceLock::new(),
+            outputs: Mutex::default(),
+        };
+    }
+
+    /// The status of the repository, from the stale cache when it's enabled.
+    pub fn summary(&self) -> Option<&Summary> {
+        return self.summary.get_or_init(|| summary_at(&self.path)).as_ref();
+    }
+
+    /// The status if a segment already asked for it.
+    pub fn gathered(&self) -> Option<&Summary> {
+        return self.summary.get()?.as_ref();
+    }
+
+    pub fn root(&self) -> Option<&str> {
+        return self.summary().map(|summary| summary.root.as_str());
+    }
+
+    pub fn branch(&self) -> Option<&str> {
+        return self.summary().map(|summary| summary.branch.as_str());
+    }
+
+    /// The repositories this one is nested in, outermost first, as for a
+    /// submodule.
+    pub fn enclosing(&self) -> &[Summary] {
+        return self.enclosing.get_or_init(|| {
+            return self.root().map(|root| enclosing(Path::new(root))).unwrap_or_default();
+        });
+    }
+
+    /// A config setting like `commit.gpgsign`, as git would resolve it.
+    /// The files are read and parsed once a render, and git is only asked
+    /// when they include others, set per-worktree settings, or the
+    /// environment adds some.
+    pub fn config(&self, key: &str) -> Option<&str> {
+        let settings = self.config.get_or_init(|| {
+            let common = Git::new(&self.path).common_dir();
+            let local = common.and_then(|common| fs::read_to_string(common.join("config")).ok()).unwrap_or_default();
+            let outer = outer_config();
+            let lower = format!("{outer}\n{local}").to_ascii_lowercase();
+            if lower.contains("[include") || lower.contains("worktreeconfig") {
+                return parse_config_list(&self.output(&["config", "--list", "-z"]).unwrap_or_default());
+            }
+            let mut settings = HashMap::new();
+            parse_config(&outer, &mut settings);
+            parse_config(&local, &mut settings);
+            return settings;
+        });
+        let key = match (key.split_once('.'), key.rsplit_once('.')) {
+            (Some((section, _)), Some((middle, name))) if middle.len() > section.len() =>
+                format!("{}{}.{}", section.to_ascii_lowercase(), &middle[section.len()..], name.to_ascii_lowercase()),
+            _ => key.to_ascii_lowercase(),
+        };
+        return settings.get(&key).map(String::as_str);
+    }
+
+    /// What `git <args>` prints in the directory, run the first time it's
+    /// asked for. Segments asking at the same moment may both run it, as
+    /// holding the lock while git runs would queue every other command.
+    pub fn output(&self, args: &[&str]) -> Option<String> {
+        let key: Vec<String> = args.iter().map(|arg| (*arg).to_owned()).collect();
+        if let Some(output) = self.outputs.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get(&key) {
+            return output.clone();
+        }
+        let output = run_command_in(&self.path, "git", args);
+        self.outputs.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(key, output.clone());
+        return output;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("", 0)]
+    #[case("0000 1111 A <a@b> 1700000000 +0000\tWIP on main: 1111 x\n", 1)]
+    #[case("0000 1111 A <a@b> 1700000000 +0000\tWIP on main\n1111 2222 A <a@b> 1700000001 +0000\tOn main: y\n\n", 2)]
+    fn test_reflog_entries(#[case] log: &str, #[case] expected: usize) {
+        let actual = reflog_entries(log);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("ref: refs/heads/feature/x\n", Some("feature/x"))]
+    #[case("0123456789abcdef0123456789abcdef01234567\n", Some("HEAD"))]
+    #[case("ref: refs/remotes/origin/main\n", None)]
+    #[case("garbage", None)]
+    fn test_head_branch(#[case] head: &str, #[case] expected: Option<&str>) {
+        let actual = head_branch(head);
+        assert_eq!(expected, actual)
+    }
+
+    const CONFIG: &str = "[core]\n\tbare = false\n[branch \"main\"]\n\tremote = origin\n\tmerge = refs/heads/main\n[branch \"local\"]\n\tremote = .\n\tmerge = refs/heads/main\n";
+
+    #[rstest]
+    #[case(CONFIG, "main", Some("refs/remotes/origin/main"))]
+    #[case(CONFIG, "local", Some("refs/heads/main"))]
+    #[case(CONFIG, "other", None)]
+    #[case("[remote \"origin\"]\n\tpushRemote = fork\n[branch \"main\"]\n\tremote = origin\n\tmerge = refs/heads/main\n", "main", None)]
+    fn test_upstream_ref(#[case] config: &str, #[case] branch: &str, #[case] expected: Option<&str>) {
+        let actual = upstream_ref(config, branch);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("[user]\n\tname = Someone\n", false)]
+    #[case("[push]\n\tdefault = current\n", true)]
+    #[case("[remote]\n\tpushDefault = fork\n", true)]
+    #[case("[include]\n\tpath = ~/.gitconfig.local\n", true)]
+    fn test_pushes_elsewhere(#[case] config: &str, #[case] expected: bool) {
+        let actual = pushes_elsewhere(config);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("[commit]\n\tgpgSign = true\n", "commit.gpgsign", Some("true"))]
+    #[case("[commit]\n\tgpgsign\n", "commit.gpgsign", Some("true"))]
+    #[case("[user]\n\tsigningkey = \"ABC 123\" # work\n", "user.signingkey", Some("ABC 123"))]
+    #[case("[gpg]\n\tformat = ssh ; for now\n", "gpg.format", Some("ssh"))]
+    #[case("[branch \"Main\"]\n\tremote = origin\n", "branch.Main.remote", Some("origin"))]
+    #[case("[commit]\n\tgpgsign = true\n[commit]\n\tgpgsign = false\n", "commit.gpgsign", Some("false"))]
+    #[case("# [commit]\n", "commit.gpgsign", None)]
+    fn test_parse_config(#[case] config: &str, #[case] key: &str, #[case] expected: Option<&str>) {
+        let mut settings = HashMap::new();
+        parse_config(config, &mut settings);
+        let actual = settings.get(key).map(String::as_str);
+        assert_eq!(expected, actual)
+    }
+
+    #[test]
+    fn test_parse_config_list() {
+        let actual = parse_config_list("commit.gpgsign\ntrue\0user.signingkey\nABC\nDEF\0core.bare\0");
+        assert_eq!(Some("true"), actual.get("commit.gpgsign").map(String::as_str));
+        assert_eq!(Some("ABC\nDEF"), actual.get("user.signingkey").map(String::as_str));
+        assert_eq!(Some("true"), actual.get("core.bare").map(String::as_str))
+    }
+
+    #[rstest]
+    #[case("ref: refs/heads/main\n", Some("refs/heads/main"))]
+    #[case("0123456789abcdef0123456789abcdef01234567\n", None)]
+    fn test_head_ref(#[case] head: &str, #[case] expected: Option<&str>) {
+        let actual = head_ref(head);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("/src/app/.git", "/src/app/.worktrees/fix-123", Some(("app", "fix-123")))]
+    #[case("/srv/app.git", "/src/fix-123", Some(("app", "fix-123")))]
+    #[case("/srv/mirror", "/src/fix-123", Some(("mirror", "fix-123")))]
+    #[case("/src/app/.git", "/", None)]
+    fn test_worktree_names(#[case] common: &str, #[case] root: &str, #[case] expected: Option<(&str, &str)>) {
+        let actual = worktree_names(Path::new(common), Path::new(root));
+        assert_eq!(expected.map(|(main, name)| (main.to_owned(), name.to_owned())), actual)
+    }
+
+    #[rstest]
+    #[case("/src/main", true, "\x1b[38;5;202m\u{E0A0}\x1b[mmain{1}")]
+    #[case("/src/main", false, "\x1b[38;5;202m\u{E0A0}\x1b[m{1}")]
+    fn test_decoration(#[case] root: &str, #[case] branch: bool, #[case] expected: &str) {
+        let summary = Summary{root: root.to_owned(), branch: "main".to_owned(), stashes: 1, ..Summary::default()};
+        let actual = summary.decoration(branch);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case(Summary::default(), State::Clean)]
+    #[case(Summary{untracked: 1, ..Summary::default()}, State::Dirty)]
+    #[case(Summary{staged: 1, ahead: 2, ..Summary::default()}, State::Dirty)]
+    #[case(Summary{behind: 1, stashes: 3, ..Summary::default()}, State::Diverged)]
+    #[case(Summary{stashes: 3, ..Summary::default()}, State::Clean)]
+    fn test_state(#[case] summary: Summary, #[case] expected: State) {
+        let actual = summary.state();
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,14 @@
+# eval "$(statusline init bash)"
+# capture $? before anything else in PROMPT_COMMAND can clobber it
+PROMPT_COMMAND="_statusline_status=\$?${PROMPT_COMMAND:+; $PROMPT_COMMAND}"
+# bash expands \! and \j before running the substitution
+PS1='$(statusline --shell bash --status "$_statusline_status" --history \! --jobs \j) '
+
+# bash has no hook for changing directory, so the builtins that do are
+# wrapped to start on the new directory's git status straight away
+_statusline_chpwd() {
+    ( statusline __refresh "cd:$PWD" &>/dev/null & )
+}
+cd() { builtin cd "$@" && _statusline_chpwd; }
+pushd() { builtin pushd "$@" && _statusline_chpwd; }
+popd() { builtin popd "$@" && _statusline_chpwd; }
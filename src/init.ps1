@@ -0,0 +1,24 @@
+# Invoke-Expression (& statusline init powershell | Out-String)
+function global:prompt {
+    # $? has to be read first, before anything here resets it
+    $status = if ($?) { 0 } elseif ($global:LASTEXITCODE) { $global:LASTEXITCODE } else { 1 }
+    $last = Get-History -Count 1
+    $history = $last.Id + 1
+    $arguments = @('--shell', 'powershell', '--status', $status, '--history', $history)
+    # a prompt redrawn without running anything has no duration
+    if ($last -and $last.Id -ne $global:_statusline_last) {
+        $global:_statusline_last = $last.Id
+        $arguments += @('--duration', ($last.EndExecutionTime - $last.StartExecutionTime).TotalSeconds.ToString([cultureinfo]::InvariantCulture))
+    }
+    $arguments += @('--jobs', @(Get-Job -State Running).Count)
+    $line = & statusline @arguments
+    "$line "
+}
+
+# start on the new directory's git status as soon as it's entered
+$ExecutionContext.InvokeCommand.LocationChangedAction = {
+    param($source, $location)
+    if ($location.NewPath.Provider.Name -eq 'FileSystem') {
+        [Diagnostics.Process]::Start('statusline', [string[]]@('__refresh', "cd:$($location.NewPath.ProviderPath)")) | Out-Null
+    }
+}
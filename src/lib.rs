@@ -0,0 +1,22 @@
+#![allow(clippy::needless_return, clippy::upper_case_acronyms)]
+//! Render a compact shell statusline: the minified working directory with
+//! VCS status, followed by whichever context segments apply.
+//!
+//! The `statusline` binary is a thin wrapper around [`statusline()`]; other
+//! tools can build a [`Context`] and render a [`Registry`] themselves.
+
+pub mod status;
+
+pub use status::{apply_vcs, git_status, git_summary, minified_path, registry, render, repo_state, report, report_timed, statusline, statusline_timed, stream, GitSegment, Options, PathSegment};
+#[cfg(unix)]
+pub use status::daemon;
+pub use status::explain::explain;
+pub use status::log;
+pub use status::bench;
+pub use status::cache;
+pub use status::config;
+pub use status::git::{Git, GitRepo, State, Summary, VCS};
+pub use status::report::{SegmentReport, StatusReport};
+pub use status::segments::{refresh, Context, Reason, Registry, Segment, StyledText, Timing};
+pub use status::builder::{Statusline, StatuslineBuilder};
+pub use status::style::{Color, Format, Shell, Span, Style};
@@ -0,0 +1,65 @@
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// Where debug messages go, if anywhere.
+enum Sink {
+    Off,
+    Stderr,
+    File(Mutex<File>),
+}
+
+static SINK: OnceLock<Sink> = OnceLock::new();
+
+/// `STATUSLINE_LOG` is a file to append to, or `1`/`stderr` for stderr.
+fn from_env() -> Sink {
+    return match env::var("STATUSLINE_LOG").as_deref() {
+        Err(_) | Ok("") | Ok("0") => Sink::Off,
+        Ok("1") | Ok("stderr") => Sink::Stderr,
+        Ok(path) => match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => Sink::File(Mutex::new(file)),
+            Err(_) => Sink::Stderr,
+        },
+    };
+}
+
+fn sink() -> &'static Sink {
+    return SINK.get_or_init(from_env);
+}
+
+/// Send messages to stderr for `--verbose`, unless `STATUSLINE_LOG`
+/// names a file. Must be called before anything is logged.
+pub fn verbose() {
+    let sink = match from_env() {
+        Sink::Off => Sink::Stderr,
+        sink => sink,
+    };
+    let _ = SINK.set(sink);
+}
+
+pub fn enabled() -> bool {
+    return !matches!(sink(), Sink::Off);
+}
+
+/// Log a message, only formatting it when logging is on.
+pub fn debug(message: impl FnOnce() -> String) {
+    match sink() {
+        Sink::Off => {},
+        Sink::Stderr => eprintln!("statusline: {}", message()),
+        Sink::File(file) => {
+            if let Ok(mut file) = file.lock() {
+                let _ = writeln!(file, "statusline[{}]: {}", std::process::id(), message());
+            }
+        },
+    }
+}
+
+/// Log a command that was run, how long it took and whether it worked.
+pub fn command(program: &str, args: &[&str], took: Duration, ok: bool) {
+    debug(|| {
+        let outcome = if ok { "ok" } else { "failed" };
+        return format!("ran `{program} {}` in {took:.1?}: {outcome}", args.join(" "));
+    });
+}
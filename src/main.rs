@@ -1,5 +1,441 @@
-mod status;
+#![allow(clippy::needless_return)]
+
+use std::env;
+use std::io::{self, IsTerminal};
+use std::panic;
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, Instant};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+
+const BASH_INIT: &str = include_str!("init.bash");
+const ZSH_INIT: &str = include_str!("init.zsh");
+const POWERSHELL_INIT: &str = include_str!("init.ps1");
+
+/// Render a compact shell statusline: the minified working directory with
+/// its git status, followed by whichever context segments apply.
+#[derive(Parser)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(flatten)]
+    output: OutputArgs,
+    #[command(flatten)]
+    prompt: PromptArgs,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// How the output is drawn, for the prompt and the subcommands alike.
+#[derive(Args)]
+struct OutputArgs {
+    /// The shell the prompt is for, so escape codes are marked as taking no room
+    #[arg(long, global = true, value_enum)]
+    shell: Option<ShellArg>,
+    /// How the output is encoded
+    #[arg(long, global = true, value_enum, default_value_t = FormatArg::Ansi)]
+    format: FormatArg,
+    /// Whether to draw in color. `auto` does for a terminal, a shell's
+    /// prompt or tmux, unless NO_COLOR is set; CLICOLOR_FORCE always does
+    #[arg(long, global = true, value_enum, default_value_t = ColorArg::Auto)]
+    color: ColorArg,
+    /// Lay the config's `[themes.<THEME>]` over the rest of it
+    #[arg(long, global = true)]
+    theme: Option<String>,
+    /// How many trailing directory names are kept whole, below the root in a repo
+    #[arg(long, global = true, value_name = "N")]
+    keep: Option<usize>,
+    /// How many directory names up to and including the repo root are kept whole
+    #[arg(long, global = true, value_name = "N")]
+    outer_keep: Option<usize>,
+    /// Log what's run and why segments are hidden, on stderr
+    #[arg(long, global = true)]
+    verbose: bool,
+}
+
+/// The directory to draw for, when it isn't the cwd.
+#[derive(Args, Default)]
+struct Target {
+    /// Draw for this directory instead of the cwd; one named like a
+    /// subcommand has to be given as `./git` or with `--path`
+    #[arg(value_name = "PATH")]
+    path: Option<PathBuf>,
+    /// Draw for this directory instead of the cwd
+    #[arg(long = "path", value_name = "PATH", conflicts_with = "path")]
+    named: Option<PathBuf>,
+}
+
+impl Target {
+    fn get(&self) -> Option<PathBuf> {
+        return self.path.clone().or_else(|| self.named.clone());
+    }
+}
+
+/// What the shell knows about its state, and how to draw the prompt.
+#[derive(Args, Default)]
+struct PromptArgs {
+    #[command(flatten)]
+    target: Target,
+    /// The exit status of the previous command
+    #[arg(long, value_name = "N", allow_negative_numbers = true)]
+    status: Option<i32>,
+    /// How long the previous command ran for, e.g. `1500ms` or `2.5`
+    #[arg(long, value_parser = duration)]
+    duration: Option<Duration>,
+    /// How many jobs the shell has in the background
+    #[arg(long, value_name = "N")]
+    jobs: Option<usize>,
+    /// The zsh keymap, `main` or `vicmd`
+    #[arg(long)]
+    keymap: Option<String>,
+    /// The shell's history event number
+    #[arg(long, value_name = "N")]
+    history: Option<u64>,
+    /// Draw slow segments as placeholders, for the first phase of an async prompt
+    #[arg(long = "async")]
+    deferred: bool,
+    /// Ask a running daemon for the prompt, rendering it here without one
+    #[arg(long)]
+    client: bool,
+    /// Print how long each segment took on stderr
+    #[arg(long)]
+    timings: bool,
+    /// Print what would be shown as JSON
+    #[arg(long)]
+    json: bool,
+    /// Say why each segment was shown or hidden
+    #[arg(long)]
+    explain: bool,
+    /// Print the bare path first and the whole statusline over it when it's ready
+    #[arg(long)]
+    stream: bool,
+    /// Print nothing, exiting 0 when the repository is clean, 1 when it has
+    /// changes, 2 when it's ahead of or behind its upstream and 3 outside one
+    #[arg(short, long)]
+    quiet: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the statusline, as running without a subcommand does
+    Prompt(PromptArgs),
+    /// Print only the git status of the repository: the branch, how far it
+    /// is from its upstream, the changes and the stashes
+    Git {
+        #[command(flatten)]
+        target: Target,
+        /// Print the status as JSON instead
+        #[arg(long)]
+        json: bool,
+        /// Print nothing, exiting with the state of the repository as
+        /// `statusline --quiet` does
+        #[arg(short, long, conflicts_with = "json")]
+        quiet: bool,
+    },
+    /// Print only the minified path
+    Path {
+        #[command(flatten)]
+        target: Target,
+        /// Draw the directory names in a color such as `cyan`, `214` or `#ff8000`
+        #[arg(long, value_name = "COLOR", value_parser = color)]
+        path_color: Option<String>,
+    },
+    /// Print the script that sets up the prompt for a shell
+    Init {
+        #[arg(value_enum)]
+        shell: ShellArg,
+    },
+    /// Print the completions of statusline's flags and subcommands for a shell
+    Completions {
+        // apart from the global `--shell`, which takes other shells
+        #[arg(id = "completion_shell", value_name = "SHELL", value_enum)]
+        shell: CompletionShell,
+    },
+    /// Print the config in effect
+    Config {
+        /// Print where the config file is read from instead
+        #[arg(long)]
+        path: bool,
+    },
+    /// Time each segment over repeated renders of the prompt
+    Bench {
+        #[command(flatten)]
+        target: Target,
+        #[arg(long, value_name = "N", default_value_t = 20)]
+        iterations: usize,
+    },
+    /// Serve prompts from a long-running process
+    Daemon,
+    /// Background work started to refresh the cache
+    #[command(name = "__refresh", hide = true)]
+    Refresh {
+        task: String,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ShellArg {
+    Bash,
+    Zsh,
+    Powershell,
+}
+
+impl From<ShellArg> for statusline::Shell {
+    fn from(shell: ShellArg) -> statusline::Shell {
+        return match shell {
+            ShellArg::Bash => statusline::Shell::Bash,
+            ShellArg::Zsh => statusline::Shell::Zsh,
+            ShellArg::Powershell => statusline::Shell::Powershell,
+        };
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    #[value(alias = "powershell")]
+    Pwsh,
+}
+
+impl From<CompletionShell> for clap_complete::Shell {
+    fn from(shell: CompletionShell) -> clap_complete::Shell {
+        return match shell {
+            CompletionShell::Bash => clap_complete::Shell::Bash,
+            CompletionShell::Zsh => clap_complete::Shell::Zsh,
+            CompletionShell::Fish => clap_complete::Shell::Fish,
+            CompletionShell::Pwsh => clap_complete::Shell::PowerShell,
+        };
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatArg {
+    Ansi,
+    Tmux,
+    Plain,
+    Html,
+}
+
+impl From<FormatArg> for statusline::Format {
+    fn from(format: FormatArg) -> statusline::Format {
+        return match format {
+            FormatArg::Ansi => statusline::Format::Ansi,
+            FormatArg::Tmux => statusline::Format::Tmux,
+            FormatArg::Plain => statusline::Format::Plain,
+            FormatArg::Html => statusline::Format::Html,
+        };
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorArg {
+    Auto,
+    Always,
+    Never,
+}
+
+/// Whether `var` is set to something other than an empty string or `0`.
+fn set(var: &str) -> bool {
+    return env::var_os(var).is_some_and(|value| !value.is_empty() && value != "0");
+}
+
+/// Whether the output is drawn in color. `auto` colors what's captured for
+/// a prompt or tmux too, as that ends up on a terminal however it's piped.
+fn colored(output: &OutputArgs) -> bool {
+    return match output.color {
+        ColorArg::Always => true,
+        ColorArg::Never => false,
+        ColorArg::Auto if set("NO_COLOR") => false,
+        ColorArg::Auto if set("CLICOLOR_FORCE") => true,
+        ColorArg::Auto => output.shell.is_some() || matches!(output.format, FormatArg::Tmux) || io::stdout().is_terminal(),
+    };
+}
+
+/// The `--format`, as plain text when the output isn't colored. HTML is
+/// only asked for on purpose, so it's left as it is.
+fn format(output: &OutputArgs) -> statusline::Format {
+    return match output.format {
+        FormatArg::Ansi | FormatArg::Tmux if !colored(output) => statusline::Format::Plain,
+        format => format.into(),
+    };
+}
+
+fn duration(text: &str) -> Result<Duration, String> {
+    return statusline::config::parse_duration(text).ok_or_else(|| format!("not a duration: {text}"));
+}
+
+fn color(name: &str) -> Result<String, String> {
+    return statusline::Color::parse(name).map(|_| name.to_owned()).ok_or_else(|| format!("not a color: {name}"));
+}
+
+/// The slowest segments first, on stderr so the prompt itself stays clean,
+/// then how long the whole render took. Segments run alongside each other,
+/// so their sum is only shown apart, as the work done.
+fn print_timings(mut timings: Vec<statusline::Timing>, elapsed: Duration) {
+    timings.sort_by_key(|timing| std::cmp::Reverse(timing.took));
+    let sum: Duration = timings.iter().map(|timing| timing.took).sum();
+    for timing in timings.into_iter().filter(|timing| timing.reason != statusline::Reason::Disabled) {
+        let note = match timing.reason {
+            statusline::Reason::Shown => String::new(),
+            reason => format!(" ({reason})"),
+        };
+        eprintln!("{:>10.1?}  {}{note}", timing.took, timing.name);
+    }
+    eprintln!("{sum:>10.1?}  sum of segments");
+    eprintln!("{elapsed:>10.1?}  total");
+}
+
+fn print_bench(summaries: Vec<statusline::bench::Summary>) {
+    println!("{:<12} {:>10} {:>10}", "segment", "p50", "p95");
+    for summary in summaries {
+        println!("{:<12} {:>10.1?} {:>10.1?}", summary.name, summary.p50, summary.p95);
+    }
+}
+
+#[cfg(unix)]
+fn serve() {
+    if let Err(error) = statusline::daemon::serve() {
+        eprintln!("statusline: {error}");
+        process::exit(1);
+    }
+}
+
+#[cfg(not(unix))]
+fn serve() {
+    eprintln!("statusline: the daemon needs Unix sockets");
+    process::exit(1);
+}
+
+#[cfg(unix)]
+fn request(options: &statusline::Options) -> Option<String> {
+    return statusline::daemon::request(options);
+}
+
+#[cfg(not(unix))]
+fn request(_options: &statusline::Options) -> Option<String> {
+    return None;
+}
+
+fn options(output: &OutputArgs, args: &PromptArgs, target: &Target) -> statusline::Options {
+    return statusline::Options{
+        keymap: args.keymap.clone(),
+        history: args.history,
+        status: args.status,
+        deferred: args.deferred,
+        keep: output.keep,
+        outer_keep: output.outer_keep,
+        duration: args.duration,
+        jobs: args.jobs,
+        format: format(output),
+        path: target.get(),
+    };
+}
+
+/// Print a line of output, wrapped for the shell it's going to.
+fn emit(text: &str, shell: Option<ShellArg>) {
+    match shell {
+        Some(shell) => println!("{}", statusline::Shell::from(shell).wrap(text)),
+        None => println!("{text}"),
+    }
+}
+
+fn prompt(args: PromptArgs, options: statusline::Options, shell: Option<ShellArg>) {
+    if args.quiet {
+        process::exit(statusline::repo_state(&options).code());
+    }
+    // fall back to rendering here when no daemon is running
+    if let Some(prompt) = args.client.then(|| request(&options)).flatten() {
+        emit(&prompt, shell);
+        return;
+    }
+    if args.explain {
+        let (report, timings) = statusline::report_timed(&options);
+        print!("{}", statusline::explain(&report, &timings));
+        return;
+    }
+    if args.json {
+        println!("{}", statusline::report(&options).to_json());
+        return;
+    }
+    if args.timings {
+        let started = Instant::now();
+        let (prompt, timings) = statusline::statusline_timed(&options);
+        let elapsed = started.elapsed();
+        emit(&prompt, shell);
+        print_timings(timings, elapsed);
+        return;
+    }
+    if args.stream {
+        let _ = statusline::stream(&options, shell.map(statusline::Shell::from), &mut io::stdout().lock());
+        return;
+    }
+    emit(&statusline::statusline(&options), shell);
+}
 
 fn main() {
-    println!("{}", status::statusline());
+    // statusline() falls back to the bare path on a panic; keep the
+    // message out of the prompt
+    panic::set_hook(Box::new(|_| {}));
+    statusline::cache::refresh_by_respawning();
+    let cli = Cli::parse();
+    let output = cli.output;
+    if output.verbose {
+        statusline::log::verbose();
+    }
+    if let Some(theme) = &output.theme {
+        // read when the config is first loaded, which hasn't happened yet
+        env::set_var("STATUSLINE_THEME", theme);
+    }
+    let defaults = |target: &Target| options(&output, &PromptArgs::default(), target);
+    match cli.command.unwrap_or(Command::Prompt(cli.prompt)) {
+        Command::Prompt(args) => {
+            let options = options(&output, &args, &args.target);
+            prompt(args, options, output.shell);
+        },
+        Command::Git{target, quiet: true, ..} => process::exit(statusline::repo_state(&defaults(&target)).code()),
+        Command::Git{target, json: true, ..} => {
+            if let Some(summary) = statusline::git_summary(&defaults(&target)) {
+                println!("{}", serde_json::to_string(&summary).unwrap_or_default());
+            }
+        },
+        Command::Git{target, ..} => {
+            if let Some(status) = statusline::git_status(&defaults(&target)) {
+                emit(&status, output.shell);
+            }
+        },
+        Command::Path{target, path_color} => emit(&statusline::minified_path(&defaults(&target), path_color.as_deref()), output.shell),
+        Command::Init{shell: ShellArg::Bash} => print!("{BASH_INIT}"),
+        Command::Init{shell: ShellArg::Zsh} => print!("{ZSH_INIT}"),
+        Command::Init{shell: ShellArg::Powershell} => print!("{POWERSHELL_INIT}"),
+        Command::Completions{shell} => {
+            clap_complete::generate(clap_complete::Shell::from(shell), &mut Cli::command(), "statusline", &mut io::stdout());
+        },
+        Command::Config{path: true} => {
+            if let Some(file) = statusline::config::config_file() {
+                println!("{}", file.display());
+            }
+        },
+        Command::Config{path: false} => print!("{}", toml::to_string(statusline::config::get()).unwrap_or_default()),
+        Command::Bench{target, iterations} => print_bench(statusline::bench::run(&defaults(&target), iterations.max(1))),
+        Command::Daemon => serve(),
+        Command::Refresh{task} => statusline::refresh(&task),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_no_color() {
+        // the only test in the binary, so no other one sees the variable
+        env::set_var("NO_COLOR", "1");
+        let cli = Cli::try_parse_from(["statusline", "--stream", "--shell", "bash", "--path", "/nonexistent/srv"]).unwrap();
+        let options = options(&cli.output, &cli.prompt, &cli.prompt.target);
+        let mut out = vec![];
+        statusline::stream(&options, cli.output.shell.map(statusline::Shell::from), &mut out).unwrap();
+        let actual = String::from_utf8(out).unwrap();
+        assert!(!actual.contains('\x1b') && actual.lines().count() == 1, "{actual:?}")
+    }
 }
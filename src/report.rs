@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+use super::git::Summary;
+use super::style::{Format, StyledText};
+
+/// One segment's output, as gathered before drawing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SegmentReport {
+    pub name: String,
+    pub text: StyledText,
+}
+
+/// Everything gathered for one prompt, independent of how it's drawn, so
+/// it can be cached, passed between processes or printed as JSON.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatusReport {
+    /// The directory the prompt was gathered for.
+    pub path: String,
+    /// The repository containing it, if the path segment looked it up.
+    pub vcs: Option<Summary>,
+    /// The segments that had something to show, in display order.
+    pub segments: Vec<SegmentReport>,
+}
+
+impl StatusReport {
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string(self).unwrap_or_default();
+    }
+
+    pub fn from_json(json: &str) -> Option<StatusReport> {
+        return serde_json::from_str(json).ok();
+    }
+
+    /// Draw the segments in `format`, with `separator` between them.
+    pub fn render(&self, format: Format, separator: &str) -> String {
+        let rendered: Vec<String> = self.segments.iter()
+            .map(|segment| segment.text.render(format))
+            .collect();
+        return rendered.join(separator);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::style::{Color, Style};
+
+    fn report() -> StatusReport {
+        return StatusReport{
+            path: "/srv/repo/src".to_owned(),
+            vcs: Some(Summary{root: "/srv/repo".to_owned(), branch: "main".to_owned(), ahead: 1, ..Summary::default()}),
+            segments: vec![
+                SegmentReport{name: "path".to_owned(), text: StyledText::new().push(Style::fg(Color::BRIGHT_BLUE), "/s/repo")},
+                SegmentReport{name: "character".to_owned(), text: StyledText::new().push(Style::default(), "$")},
+            ],
+        };
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let expected = report();
+        let actual = StatusReport::from_json(&expected.to_json());
+        assert_eq!(Some(expected), actual)
+    }
+
+    #[test]
+    fn test_render() {
+        let actual = report().render(Format::Plain, " ");
+        assert_eq!("/s/repo $", actual)
+    }
+}
@@ -0,0 +1,188 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+use crate::status::{cache, config};
+use super::{Context, color, glob_match};
+
+const ICON: &str = "\u{F0E0F} ";
+const DEFAULT_COLOR: &str = "\x1b[33m";
+
+fn config_file() -> Option<PathBuf> {
+    if let Some(file) = env::var_os("AWS_CONFIG_FILE") {
+        return Some(PathBuf::from(file));
+    }
+    return env::var_os("HOME").map(|home| Path::new(&home).join(".aws/config"));
+}
+
+fn sso_cache_dir() -> Option<PathBuf> {
+    return env::var_os("HOME").map(|home| Path::new(&home).join(".aws/sso/cache"));
+}
+
+/// Find `wanted` in `section` of an `~/.aws/config` style ini file.
+fn parse_setting(contents: &str, section: &str, wanted: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in contents.lines().map(str::trim) {
+        if let Some(header) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            in_section = header.trim() == section;
+        } else if in_section {
+            if let Some((key, value)) = line.split_once('=') {
+                if key.trim() == wanted {
+                    return Some(value.trim().to_owned());
+                }
+            }
+        }
+    }
+    return None;
+}
+
+fn profile_section(profile: &str) -> String {
+    return match profile {
+        "default" => "default".to_owned(),
+        _ => format!("profile {profile}"),
+    };
+}
+
+/// Find `region` in the profile's section of an `~/.aws/config` style ini file.
+fn parse_region(contents: &str, profile: &str) -> Option<String> {
+    return parse_setting(contents, &profile_section(profile), "region");
+}
+
+/// The SSO portal a profile signs in through, set on the profile itself or
+/// on the `[sso-session]` it names.
+fn sso_start_url(contents: &str, profile: &str) -> Option<String> {
+    let section = profile_section(profile);
+    return match parse_setting(contents, &section, "sso_session") {
+        Some(session) => parse_setting(contents, &format!("sso-session {session}"), "sso_start_url"),
+        None => parse_setting(contents, &section, "sso_start_url"),
+    };
+}
+
+/// Seconds since the epoch for a UTC timestamp like `2024-05-01T12:34:56Z`,
+/// as the SSO cache writes them, `UTC` suffix and all for older CLIs.
+fn parse_timestamp(text: &str) -> Option<u64> {
+    let number = |range: std::ops::Range<usize>| text.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month) {
+        return None;
+    }
+    // days from the civil calendar date, counted from 1970-01-01
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    return u64::try_from(days * 86400 + hour * 3600 + minute * 60 + second).ok();
+}
+
+/// When the latest access token cached for `start_url` by `aws sso login`
+/// expires, or None when there isn't one.
+fn sso_expiry(start_url: &str) -> Option<u64> {
+    let entries = fs::read_dir(sso_cache_dir()?).ok()?;
+    return entries.flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|extension| extension == "json"))
+        .filter_map(|entry| serde_json::from_str::<serde_json::Value>(&fs::read_to_string(entry.path()).ok()?).ok())
+        .filter(|token| token["startUrl"].as_str() == Some(start_url) && token["accessToken"].is_string())
+        .filter_map(|token| parse_timestamp(token["expiresAt"].as_str()?))
+        .max();
+}
+
+/// The color of the first `[[aws.rules]]` entry whose `profile` glob matches.
+fn profile_color(profile: &str) -> String {
+    let rules = config::value("aws.rules").and_then(Value::as_array);
+    for rule in rules.into_iter().flatten() {
+        let pattern = rule.get("profile").and_then(Value::as_str).unwrap_or("*");
+        if glob_match(pattern, profile) {
+            if let Some(color) = rule.get("color").and_then(Value::as_str).and_then(color) {
+                return color;
+            }
+        }
+    }
+    return DEFAULT_COLOR.to_owned();
+}
+
+/// Show the AWS profile and region that the CLI and SDKs would use, with a
+/// red `✗` when the profile signs in through SSO and the token `aws sso
+/// login` cached for it has expired or is missing.
+pub fn profile(_ctx: &Context) -> Option<String> {
+    let profile = ["AWS_PROFILE", "AWS_DEFAULT_PROFILE", "AWS_VAULT"].iter()
+        .find_map(|var| env::var(var).ok().filter(|value| !value.is_empty()));
+    let env_region = ["AWS_REGION", "AWS_DEFAULT_REGION"].iter()
+        .find_map(|var| env::var(var).ok().filter(|value| !value.is_empty()));
+    let contents = profile.as_ref().and_then(|_| fs::read_to_string(config_file()?).ok()).unwrap_or_default();
+    let mut text = match (&profile, env_region) {
+        (Some(profile), Some(region)) => format!("{profile}({region})"),
+        (Some(profile), None) => match parse_region(&contents, profile) {
+            Some(region) => format!("{profile}({region})"),
+            None => profile.to_owned(),
+        },
+        // a region on its own still changes where commands go
+        (None, Some(region)) => region,
+        (None, None) => return None,
+    };
+    let start_url = profile.as_deref().and_then(|profile| sso_start_url(&contents, profile));
+    if start_url.is_some_and(|url| sso_expiry(&url).is_none_or(|expiry| expiry <= cache::now())) {
+        text += "\x1b[31m\u{2717}";
+    }
+    let color = profile_color(profile.as_deref().unwrap_or_default());
+    return Some(format!("{color}{ICON}{text}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const CONFIG: &str = "
+[default]
+region = us-east-1
+
+[profile prod]
+output = json
+region = eu-west-2
+
+[profile dev]
+output = json
+
+[profile sso]
+sso_session = corp
+
+[profile legacy]
+sso_start_url = https://old.awsapps.com/start
+
+[sso-session corp]
+sso_start_url = https://corp.awsapps.com/start
+";
+
+    #[rstest]
+    #[case("default", Some("us-east-1"))]
+    #[case("prod", Some("eu-west-2"))]
+    #[case("dev", None)]
+    #[case("missing", None)]
+    fn test_parse_region(#[case] profile: &str, #[case] expected: Option<&str>) {
+        let actual = parse_region(CONFIG, profile);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("sso", Some("https://corp.awsapps.com/start"))]
+    #[case("legacy", Some("https://old.awsapps.com/start"))]
+    #[case("prod", None)]
+    fn test_sso_start_url(#[case] profile: &str, #[case] expected: Option<&str>) {
+        let actual = sso_start_url(CONFIG, profile);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("1970-01-01T00:00:00Z", Some(0))]
+    #[case("2024-02-29T12:34:56Z", Some(1709210096))]
+    #[case("2019-11-14T04:05:45UTC", Some(1573704345))]
+    #[case("yesterday", None)]
+    fn test_parse_timestamp(#[case] input: &str, #[case] expected: Option<u64>) {
+        let actual = parse_timestamp(input);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+use regex::Regex;
+
+use crate::status::config;
+use super::{Context, run_command};
+
+#[derive(Debug, PartialEq)]
+struct Battery {
+    percent: u8,
+    charging: bool,
+}
+
+/// Read the first battery under `/sys/class/power_supply`.
+fn read_sysfs(root: &Path) -> Option<Battery> {
+    let mut entries: Vec<_> = fs::read_dir(root).ok()?.flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("BAT"))
+        .collect();
+    entries.sort_by_key(|entry| entry.file_name());
+    let dir = entries.first()?.path();
+    let percent = fs::read_to_string(dir.join("capacity")).ok()?.trim().parse().ok()?;
+    let status = fs::read_to_string(dir.join("status")).unwrap_or_default();
+    return Some(Battery{
+        percent,
+        charging: status.trim() == "Charging",
+    });
+}
+
+static PMSET: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d+)%; (\w+)").unwrap());
+
+/// Parse macOS `pmset -g batt`, e.g. `-InternalBattery-0 (id=1) 85%; charging; 1:02 remaining`.
+fn parse_pmset(output: &str) -> Option<Battery> {
+    let caps = PMSET.captures(output)?;
+    return Some(Battery{
+        percent: caps[1].parse().ok()?,
+        charging: &caps[2] == "charging",
+    });
+}
+
+fn glyph(battery: &Battery) -> char {
+    if battery.charging {
+        return '\u{F0084}';
+    }
+    // nerd font battery icons step in tenths from 10% (U+F007A) to full
+    // (U+F0079), past which some drivers still report
+    return match battery.percent.min(100) / 10 {
+        0 => '\u{F008E}',
+        10 => '\u{F0079}',
+        tenth => char::from_u32(0xF007A + u32::from(tenth) - 1).unwrap_or('\u{F0079}'),
+    };
+}
+
+/// Show the battery charge when `battery.enabled` is set, colored by the
+/// `battery.warning` and `battery.critical` percentages.
+pub fn status(_ctx: &Context) -> Option<String> {
+    if !config::bool("battery.enabled", false) {
+        return None;
+    }
+    let battery = read_sysfs(Path::new("/sys/class/power_supply"))
+        .or_else(|| parse_pmset(&run_command("pmset", &["-g", "batt"])?))?;
+    let percent = i64::from(battery.percent);
+    let color = if battery.charging {
+        "\x1b[32m"
+    } else if percent <= config::integer("battery.critical", 10) {
+        "\x1b[31m"
+    } else if percent <= config::integer("battery.warning", 30) {
+        "\x1b[33m"
+    } else {
+        "\x1b[32m"
+    };
+    return Some(format!("{color}{}{}%\x1b[m", glyph(&battery), battery.percent));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("Now drawing from 'Battery Power'\n -InternalBattery-0 (id=4653155)\t85%; discharging; 4:04 remaining present: true", Some(Battery{percent: 85, charging: false}))]
+    #[case(" -InternalBattery-0 (id=4653155)\t100%; charged; 0:00 remaining present: true", Some(Battery{percent: 100, charging: false}))]
+    #[case(" -InternalBattery-0 (id=4653155)\t42%; charging; 1:02 remaining present: true", Some(Battery{percent: 42, charging: true}))]
+    #[case("Now drawing from 'AC Power'", None)]
+    fn test_parse_pmset(#[case] input: &str, #[case] expected: Option<Battery>) {
+        let actual = parse_pmset(input);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case(5, false, '\u{F008E}')]
+    #[case(15, false, '\u{F007A}')]
+    #[case(95, false, '\u{F0082}')]
+    #[case(100, false, '\u{F0079}')]
+    #[case(115, false, '\u{F0079}')]
+    #[case(255, false, '\u{F0079}')]
+    #[case(50, true, '\u{F0084}')]
+    fn test_glyph(#[case] percent: u8, #[case] charging: bool, #[case] expected: char) {
+        let actual = glyph(&Battery{percent, charging});
+        assert_eq!(expected, actual)
+    }
+}
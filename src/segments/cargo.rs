@@ -0,0 +1,71 @@
+use std::fs;
+use toml::Table;
+
+use crate::status::cache;
+use super::{Context, project};
+
+const ICON: &str = "\x1b[38;5;208m\u{F487}";
+
+/// Read `key` from the `[package]` table, following `key.workspace = true`
+/// up to the `[workspace.package]` table of the workspace root.
+fn package_key(manifest: &Table, workspace: Option<&Table>, key: &str) -> Option<String> {
+    let value = manifest.get("package")?.get(key)?;
+    if let Some(value) = value.as_str() {
+        return Some(value.to_owned());
+    }
+    if value.get("workspace")?.as_bool()? {
+        let inherited = workspace?.get("workspace")?.get("package")?.get(key)?;
+        return inherited.as_str().map(str::to_owned);
+    }
+    return None;
+}
+
+fn parse_manifest(contents: &str, workspace: Option<&str>) -> Option<String> {
+    let manifest = contents.parse::<Table>().ok()?;
+    let workspace = workspace.and_then(|contents| contents.parse::<Table>().ok());
+    let name = package_key(&manifest, workspace.as_ref(), "name")?;
+    return match package_key(&manifest, workspace.as_ref(), "version") {
+        Some(version) => Some(format!("{name} v{version}")),
+        None => Some(name),
+    };
+}
+
+/// Show `name vX.Y.Z` for the closest Cargo package, cached on the manifest mtime.
+pub fn package(ctx: &Context) -> Option<String> {
+    let manifests = project::files("rust");
+    let manifest = ctx.find_upwards(&manifests)?;
+    let workspace = manifest.parent()?.parent()
+        .and_then(|parent| ctx.find_upwards_from(parent, &manifests));
+    let mut fingerprint = cache::mtime(&manifest)?;
+    if let Some(workspace) = &workspace {
+        fingerprint += ":";
+        fingerprint += &cache::mtime(workspace).unwrap_or_default();
+    }
+    let key = format!("cargo:{}", manifest.display());
+    let package = cache::get_or_insert(&key, &fingerprint, || {
+        let contents = fs::read_to_string(&manifest).ok()?;
+        let workspace = workspace.and_then(|workspace| fs::read_to_string(workspace).ok());
+        return parse_manifest(&contents, workspace.as_deref());
+    })?;
+    return Some(format!("{ICON}{package}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("[package]\nname = \"statusline\"\nversion = \"0.3.1\"\n", None, Some("statusline v0.3.1"))]
+    #[case("[package]\nname = \"statusline\"\n", None, Some("statusline"))]
+    #[case("[workspace]\nmembers = [\"a\"]\n", None, None)]
+    #[case(
+        "[package]\nname = \"member\"\nversion.workspace = true\n",
+        Some("[workspace.package]\nversion = \"1.2.0\"\n"),
+        Some("member v1.2.0"),
+    )]
+    fn test_parse_manifest(#[case] input: &str, #[case] workspace: Option<&str>, #[case] expected: Option<&str>) {
+        let actual = parse_manifest(input, workspace);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+}
@@ -0,0 +1,55 @@
+use crate::status::config;
+use super::Context;
+
+#[cfg(unix)]
+fn is_root() -> bool {
+    return unsafe { libc::geteuid() } == 0;
+}
+
+#[cfg(not(unix))]
+fn is_root() -> bool {
+    return false;
+}
+
+/// The prompt character: `#` for root, `❮` in vi command mode, `❯` otherwise,
+/// each overridable with `character.root`, `character.vicmd` and `character.symbol`.
+fn symbol(ctx: &Context, root: bool) -> &'static str {
+    if root {
+        return config::string("character.root").unwrap_or("#");
+    }
+    if ctx.options.keymap.as_deref() == Some("vicmd") {
+        return config::string("character.vicmd").unwrap_or("\u{276E}");
+    }
+    return config::string("character.symbol").unwrap_or("\u{276F}");
+}
+
+/// End the prompt with the prompt character, red when the previous command
+/// failed. Only shown when the shell integration passes `--status`, so
+/// prompts that add their own `$` are left alone.
+pub fn prompt(ctx: &Context) -> Option<String> {
+    let status = ctx.options.status?;
+    let color = if status == 0 { "\x1b[32m" } else { "\x1b[31m" };
+    return Some(format!("{color}{}\x1b[m", symbol(ctx, is_root())));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use crate::status::Options;
+
+    #[rstest]
+    #[case(None, false, "\u{276F}")]
+    #[case(Some("main"), false, "\u{276F}")]
+    #[case(Some("vicmd"), false, "\u{276E}")]
+    #[case(Some("vicmd"), true, "#")]
+    fn test_symbol(#[case] keymap: Option<&str>, #[case] root: bool, #[case] expected: &str) {
+        let options = Options{
+            keymap: keymap.map(str::to_owned),
+            ..Options::default()
+        };
+        let ctx = Context::new("/".into(), options);
+        let actual = symbol(&ctx, root);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,70 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use super::Context;
+
+const ICON: &str = "\x1b[36m\u{F4B7}";
+
+/// Read `name="..."` from podman's `/run/.containerenv`.
+fn parse_containerenv(contents: &str) -> Option<String> {
+    let line = contents.lines().find_map(|line| line.strip_prefix("name="))?;
+    let name = line.trim_matches('"');
+    if name.is_empty() {
+        return None;
+    }
+    return Some(name.to_owned());
+}
+
+/// Whether pid 1's cgroups point at a container runtime.
+fn cgroup_container(contents: &str) -> bool {
+    return contents.lines().any(|line| {
+        ["docker", "kubepods", "containerd", "lxc", "libpod"].iter().any(|runtime| line.contains(runtime))
+    });
+}
+
+fn hostname() -> Option<String> {
+    let name = fs::read_to_string("/etc/hostname").ok()?;
+    return Some(name.trim().to_owned()).filter(|name| !name.is_empty());
+}
+
+/// Show a container glyph and name when the shell itself runs inside a container.
+pub fn indicator(_ctx: &Context) -> Option<String> {
+    let name = if let Ok(contents) = fs::read_to_string("/run/.containerenv") {
+        parse_containerenv(&contents).or_else(hostname)
+    } else if Path::new("/.dockerenv").exists()
+        || env::var_os("container").is_some()
+        || fs::read_to_string("/proc/1/cgroup").is_ok_and(|contents| cgroup_container(&contents)) {
+        hostname()
+    } else {
+        return None;
+    };
+    return match name {
+        Some(name) => Some(format!("{ICON} {name}\x1b[m")),
+        None => Some(format!("{ICON}\x1b[m")),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("engine=\"podman-4.9.3\"\nname=\"fedora-toolbox-39\"\nid=\"abc\"\n", Some("fedora-toolbox-39"))]
+    #[case("engine=\"podman-4.9.3\"\nname=\"\"\n", None)]
+    #[case("", None)]
+    fn test_parse_containerenv(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_containerenv(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("12:pids:/docker/3f2a\n", true)]
+    #[case("0::/kubepods/besteffort/pod1\n", true)]
+    #[case("0::/init.scope\n", false)]
+    fn test_cgroup_container(#[case] input: &str, #[case] expected: bool) {
+        let actual = cgroup_container(input);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,55 @@
+use std::process::Command;
+use std::time::Duration;
+use toml::{Table, Value};
+
+use crate::status::config;
+use super::{Context, cached_for, color, run_with_timeout};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[cfg(unix)]
+fn shell(script: &str) -> Command {
+    let mut command = Command::new("sh");
+    command.args(["-c", script]);
+    return command;
+}
+
+#[cfg(not(unix))]
+fn shell(script: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.args(["/C", script]);
+    return command;
+}
+
+/// Run one `[segment.custom.<name>]` entry. With `interval` set the output is
+/// reused until it's that old; `timeout` (default 1s) bounds each run.
+fn render_one(ctx: &Context, name: &str, segment: &Table) -> Option<String> {
+    let script = segment.get("command")?.as_str()?;
+    let timeout = segment.get("timeout").and_then(config::duration).unwrap_or(DEFAULT_TIMEOUT);
+    let run = || {
+        let mut command = shell(script);
+        command.current_dir(&ctx.path);
+        return run_with_timeout(command, timeout);
+    };
+    let key = format!("custom:{name}:{script}:{}", ctx.path.display());
+    let output = cached_for(&key, segment.get("interval").and_then(config::duration), run)?;
+    let output = output.lines().next().unwrap_or_default().trim();
+    if output.is_empty() {
+        return None;
+    }
+    let style = segment.get("style").and_then(Value::as_str).and_then(color).unwrap_or_default();
+    return Some(format!("{style}{output}\x1b[m"));
+}
+
+/// Show the first line of stdout of each command configured under
+/// `[segment.custom.<name>]`, hiding those that print nothing or fail.
+pub fn commands(ctx: &Context) -> Option<String> {
+    let segments = config::value("segment.custom").and_then(Value::as_table)?;
+    let rendered: Vec<String> = segments.iter()
+        .filter_map(|(name, segment)| render_one(ctx, name, segment.as_table()?))
+        .collect();
+    if rendered.is_empty() {
+        return None;
+    }
+    return Some(rendered.join(" "));
+}
@@ -0,0 +1,92 @@
+#[cfg(unix)]
+use std::ffi::CString;
+#[cfg(unix)]
+use std::mem::MaybeUninit;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use crate::status::config;
+
+use super::Context;
+
+const ICON: &str = "\u{F02CA}";
+
+struct Usage {
+    available: u64,
+    total: u64,
+}
+
+// the statvfs field types differ between platforms
+#[allow(clippy::unnecessary_cast)]
+#[cfg(unix)]
+fn statvfs(path: &Path) -> Option<Usage> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: path is NUL terminated and stat is only read after statvfs succeeds
+    if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let stat = unsafe { stat.assume_init() };
+    let fragment = stat.f_frsize as u64;
+    return Some(Usage{
+        available: stat.f_bavail as u64 * fragment,
+        total: stat.f_blocks as u64 * fragment,
+    });
+}
+
+#[cfg(not(unix))]
+fn statvfs(_path: &Path) -> Option<Usage> {
+    return None;
+}
+
+/// Format a byte count with a binary unit suffix, e.g. `3.2G`.
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 || size >= 10.0 {
+        return format!("{size:.0}{}", UNITS[unit]);
+    }
+    return format!("{size:.1}{}", UNITS[unit]);
+}
+
+/// Show the free space on the cwd's filesystem once it drops below
+/// `disk.threshold` percent (default 10), red below half of that.
+/// Disable with `disk.enabled = false`.
+pub fn free(ctx: &Context) -> Option<String> {
+    if !config::bool("disk.enabled", true) {
+        return None;
+    }
+    let usage = statvfs(&ctx.path)?;
+    if usage.total == 0 {
+        return None;
+    }
+    let percent = usage.available as f64 * 100.0 / usage.total as f64;
+    let threshold = config::float("disk.threshold", 10.0);
+    if percent >= threshold {
+        return None;
+    }
+    let color = if percent < threshold / 2.0 { "\x1b[31m" } else { "\x1b[33m" };
+    return Some(format!("{color}{ICON}{}\x1b[m", human_size(usage.available)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(512, "512B")]
+    #[case(1536, "1.5K")]
+    #[case(3435973837, "3.2G")]
+    #[case(53687091200, "50G")]
+    fn test_human_size(#[case] input: u64, #[case] expected: &str) {
+        let actual = human_size(input);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,112 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+
+use super::{Context, version_cmp, which};
+
+const ICON: &str = "\x1b[35m\u{E77F}";
+
+fn parse_global_json(contents: &str) -> Option<String> {
+    let global: Value = serde_json::from_str(contents).ok()?;
+    return global.get("sdk")?.get("version")?.as_str().map(str::to_owned);
+}
+
+fn feature_band(version: &str) -> Option<(u64, u64, u64, u64)> {
+    let mut parts = version.split(['.', '-']).map(|part| part.parse::<u64>().ok());
+    let (major, minor, patch) = (parts.next()??, parts.next()??, parts.next()??);
+    return Some((major, minor, patch / 100, patch % 100));
+}
+
+/// Whether `installed` satisfies a global.json request under the default
+/// `latestPatch` roll-forward: same feature band, same or later patch.
+fn satisfies(installed: &str, requested: &str) -> bool {
+    let (Some(installed), Some(requested)) = (feature_band(installed), feature_band(requested)) else {
+        return installed == requested;
+    };
+    return installed.0 == requested.0 && installed.1 == requested.1
+        && installed.2 == requested.2 && installed.3 >= requested.3;
+}
+
+fn dotnet_root() -> Option<PathBuf> {
+    if let Some(root) = env::var_os("DOTNET_ROOT") {
+        return Some(PathBuf::from(root));
+    }
+    let executable = fs::canonicalize(which("dotnet")?).ok()?;
+    return executable.parent().map(Path::to_path_buf);
+}
+
+/// SDK versions installed under `<dotnet root>/sdk`, read without starting dotnet.
+fn installed_sdks() -> Vec<String> {
+    let Some(entries) = dotnet_root().and_then(|root| fs::read_dir(root.join("sdk")).ok()) else {
+        return vec![];
+    };
+    let mut sdks: Vec<String> = entries.flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    sdks.sort_by(|a, b| version_cmp(a, b));
+    return sdks;
+}
+
+/// Project files have arbitrary names, so only the cwd is listed rather than
+/// every parent as with the fixed-name markers.
+fn has_project_file(ctx: &Context) -> bool {
+    let Ok(entries) = fs::read_dir(&ctx.path) else {
+        return false;
+    };
+    return entries.flatten().any(|entry| {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        return name.ends_with(".csproj") || name.ends_with(".fsproj") || name.ends_with(".sln");
+    });
+}
+
+/// Show the SDK pinned by global.json, in red with a `!` when no installed SDK
+/// satisfies it, or the newest installed SDK for unpinned .NET projects.
+pub fn sdk(ctx: &Context) -> Option<String> {
+    let global = ctx.find_upwards(&["global.json"]);
+    let project = has_project_file(ctx);
+    // outside .NET projects the SDKs aren't worth listing
+    if global.is_none() && !project {
+        return None;
+    }
+    let sdks = installed_sdks();
+    if let Some(global) = global {
+        if let Some(requested) = parse_global_json(&fs::read_to_string(global).ok()?) {
+            if !sdks.iter().any(|sdk| satisfies(sdk, &requested)) {
+                return Some(format!("{ICON}\x1b[91m{requested}!\x1b[m"));
+            }
+            return Some(format!("{ICON}{requested}\x1b[m"));
+        }
+    }
+    if !project {
+        return None;
+    }
+    return Some(format!("{ICON}{}\x1b[m", sdks.last()?));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(r#"{"sdk": {"version": "8.0.100", "rollForward": "latestPatch"}}"#, Some("8.0.100"))]
+    #[case(r#"{"msbuild-sdks": {}}"#, None)]
+    fn test_parse_global_json(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_global_json(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("8.0.100", "8.0.100", true)]
+    #[case("8.0.104", "8.0.100", true)]
+    #[case("8.0.204", "8.0.100", false)]
+    #[case("8.0.100", "8.0.101", false)]
+    #[case("7.0.410", "8.0.100", false)]
+    fn test_satisfies(#[case] installed: &str, #[case] requested: &str, #[case] expected: bool) {
+        let actual = satisfies(installed, requested);
+        assert_eq!(expected, actual)
+    }
+}
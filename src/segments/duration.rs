@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use crate::status::config;
+use super::Context;
+
+const ICON: &str = "\x1b[33m\u{F520} ";
+
+/// `1h2m`, `3m4s` or `5.2s`, to the precision that matters at that length.
+fn human(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    return match seconds {
+        0..60 => format!("{:.1}s", duration.as_secs_f64()),
+        60..3600 => format!("{}m{}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{}m", seconds / 3600, seconds % 3600 / 60),
+    };
+}
+
+/// How long the previous command took, passed with `--duration`, once
+/// it's over `duration.threshold` (2s by default).
+pub fn elapsed(ctx: &Context) -> Option<String> {
+    let duration = ctx.options.duration?;
+    let threshold = config::value("duration.threshold").and_then(config::duration).unwrap_or(Duration::from_secs(2));
+    if duration < threshold {
+        return None;
+    }
+    return Some(format!("{ICON}{}\x1b[m", human(duration)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Duration::from_millis(5250), "5.2s")]
+    #[case(Duration::from_secs(184), "3m4s")]
+    #[case(Duration::from_secs(3720), "1h2m")]
+    fn test_human(#[case] input: Duration, #[case] expected: &str) {
+        let actual = human(input);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,78 @@
+use std::ffi::{c_char, CStr, CString};
+use libloading::{Library, Symbol};
+use toml::Value;
+
+use crate::status::config;
+use super::{Context, Registry, Segment, StyledText};
+use super::plugin::context_json;
+
+/// Renders a segment from the JSON context, returning a NUL-terminated
+/// string (or null for nothing) owned by the library.
+type RenderFn = unsafe extern "C" fn(*const c_char) -> *mut c_char;
+/// Releases a string returned by the render function.
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+const RENDER: &[u8] = b"statusline_render\0";
+const FREE: &[u8] = b"statusline_free\0";
+
+/// A segment rendered in-process by a shared library exporting
+/// `statusline_render` and `statusline_free` with the C ABI.
+pub struct Dylib {
+    name: String,
+    library: Library,
+}
+
+impl Dylib {
+    fn open(name: &str, path: &str) -> Option<Dylib> {
+        // SAFETY: loading runs the library's initialisers; the user vouched
+        // for it by listing it in their config.
+        let library = unsafe { Library::new(path) }.ok()?;
+        // check the entry points up front rather than on every prompt
+        unsafe {
+            library.get::<RenderFn>(RENDER).ok()?;
+            library.get::<FreeFn>(FREE).ok()?;
+        }
+        return Some(Dylib{name: name.to_owned(), library});
+    }
+}
+
+impl Segment for Dylib {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    fn render(&self, ctx: &Context) -> Option<StyledText> {
+        let input = CString::new(context_json(&self.name, ctx)).ok()?;
+        // SAFETY: the symbols were checked in `open` and have the
+        // documented signatures; the result is freed by its own library.
+        unsafe {
+            let render: Symbol<RenderFn> = self.library.get(RENDER).ok()?;
+            let free: Symbol<FreeFn> = self.library.get(FREE).ok()?;
+            let output = render(input.as_ptr());
+            if output.is_null() {
+                return None;
+            }
+            let text = CStr::from_ptr(output).to_string_lossy().into_owned();
+            free(output);
+            if text.is_empty() {
+                return None;
+            }
+            return Some(StyledText::from_ansi(&text));
+        }
+    }
+}
+
+/// Load the libraries in `[plugins.libraries]`, a table of segment name to
+/// path; ones that fail to load or lack the entry points are skipped.
+pub fn load() -> Registry {
+    let mut registry = Registry::default();
+    let Some(libraries) = config::value("plugins.libraries").and_then(Value::as_table) else {
+        return registry;
+    };
+    for (name, path) in libraries {
+        if let Some(dylib) = path.as_str().and_then(|path| Dylib::open(name, path)) {
+            registry.register(dylib);
+        }
+    }
+    return registry;
+}
@@ -0,0 +1,64 @@
+use std::sync::LazyLock;
+use regex::Regex;
+
+use super::{Context, cached_output, project, tools};
+
+const ICON: &str = "\x1b[35m\u{E62D}";
+
+static VERSION_OUTPUT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"Elixir (\S+) \(compiled with Erlang/OTP (\d+)\)").unwrap());
+
+/// Parse the Elixir and OTP releases from `elixir --version`.
+fn parse_elixir_version(output: &str) -> Option<(String, String)> {
+    let caps = VERSION_OUTPUT.captures(output)?;
+    return Some((caps[1].to_owned(), caps[2].to_owned()));
+}
+
+/// Split asdf's `1.16.0-otp-26` naming into the Elixir and OTP releases.
+fn split_otp(version: &str) -> (String, Option<String>) {
+    return match version.split_once("-otp-") {
+        Some((elixir, otp)) => (elixir.to_owned(), Some(otp.to_owned())),
+        None => (version.to_owned(), None),
+    };
+}
+
+/// The Elixir/OTP pair from `.tool-versions`, falling back to `elixir --version`
+/// cached against the executable's mtime.
+fn versions(ctx: &Context) -> Option<(String, String)> {
+    for dir in ctx.path.ancestors() {
+        if let Some(elixir) = tools::tool_version(dir, "elixir") {
+            let (elixir, otp) = split_otp(&elixir);
+            let otp = otp.or_else(|| tools::tool_version(dir, "erlang"))?;
+            return Some((elixir, otp));
+        }
+    }
+    return parse_elixir_version(&cached_output("elixir", &["--version"])?);
+}
+
+/// Show the Elixir and OTP releases in Mix projects.
+pub fn version(ctx: &Context) -> Option<String> {
+    ctx.find_upwards(&project::files("elixir"))?;
+    let (elixir, otp) = versions(ctx)?;
+    return Some(format!("{ICON}{elixir}\x1b[90m/otp{otp}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("Erlang/OTP 26 [erts-14.2.1] [source] [64-bit]\n\nElixir 1.16.0 (compiled with Erlang/OTP 26)", Some(("1.16.0", "26")))]
+    #[case("elixir: command not found", None)]
+    fn test_parse_elixir_version(#[case] input: &str, #[case] expected: Option<(&str, &str)>) {
+        let actual = parse_elixir_version(input);
+        assert_eq!(expected.map(|(a, b)| (a.to_owned(), b.to_owned())), actual)
+    }
+
+    #[rstest]
+    #[case("1.16.0-otp-26", "1.16.0", Some("26"))]
+    #[case("1.15.7", "1.15.7", None)]
+    fn test_split_otp(#[case] input: &str, #[case] elixir: &str, #[case] otp: Option<&str>) {
+        let actual = split_otp(input);
+        assert_eq!((elixir.to_owned(), otp.map(str::to_owned)), actual)
+    }
+}
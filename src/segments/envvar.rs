@@ -0,0 +1,59 @@
+use std::env;
+use toml::{Table, Value};
+
+use crate::status::config;
+use super::{Context, color};
+
+/// Fill `{label}`, `{name}` and `{value}` into a `[segment.env.*]` format.
+fn apply_format(format: &str, label: &str, name: &str, value: &str) -> String {
+    return format
+        .replace("{label}", label)
+        .replace("{name}", name)
+        .replace("{value}", value);
+}
+
+fn render_one(name: &str, segment: &Table) -> Option<String> {
+    let get = |key: &str| segment.get(key).and_then(Value::as_str);
+    let variable = get("variable").unwrap_or(name);
+    let value = env::var(variable).unwrap_or_default();
+    let show_empty = segment.get("show_empty").and_then(Value::as_bool).unwrap_or(false);
+    if value.is_empty() && !show_empty {
+        return None;
+    }
+    let text = apply_format(
+        get("format").unwrap_or("{label}{value}"),
+        get("label").unwrap_or_default(),
+        variable,
+        &value,
+    );
+    let style = get("style").and_then(color).unwrap_or_default();
+    return Some(format!("{style}{text}\x1b[m"));
+}
+
+/// Show environment variables declared under `[segment.env.<name>]`, e.g.
+/// `variable = "ENVIRONMENT"` with `label = "env:"` renders `env:staging`.
+pub fn variables(_ctx: &Context) -> Option<String> {
+    let segments = config::value("segment.env").and_then(Value::as_table)?;
+    let rendered: Vec<String> = segments.iter()
+        .filter_map(|(name, segment)| render_one(name, segment.as_table()?))
+        .collect();
+    if rendered.is_empty() {
+        return None;
+    }
+    return Some(rendered.join(" "));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("{label}{value}", "env:", "ENVIRONMENT", "staging", "env:staging")]
+    #[case("{name}={value}", "", "STAGE", "prod", "STAGE=prod")]
+    #[case("[{value}]", "ignored", "X", "", "[]")]
+    fn test_apply_format(#[case] format: &str, #[case] label: &str, #[case] name: &str, #[case] value: &str, #[case] expected: &str) {
+        let actual = apply_format(format, label, name, value);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,81 @@
+use std::cmp::Ordering;
+use std::fs;
+
+use crate::status::config;
+use super::{Context, project, run_command_in, version_cmp};
+
+const ICON: &str = "\x1b[36m\u{E627}";
+
+struct GoMod {
+    module: Option<String>,
+    go: Option<String>,
+}
+
+fn parse_go_mod(contents: &str) -> GoMod {
+    let mut result = GoMod{
+        module: None,
+        go: None,
+    };
+    for line in contents.lines() {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("module"), Some(module)) => result.module = Some(module.trim_matches('"').to_owned()),
+            (Some("go"), Some(version)) => result.go = Some(version.to_owned()),
+            _ => {},
+        }
+    }
+    return result;
+}
+
+/// Parse `go version go1.22.1 linux/amd64` into `1.22.1`.
+fn parse_go_version(output: &str) -> Option<String> {
+    let word = output.split_whitespace().nth(2)?;
+    return word.strip_prefix("go").map(str::to_owned);
+}
+
+/// Show the module name and its Go directive when inside a Go module. With
+/// `go.show_installed` the installed toolchain is shown too, in red when it is
+/// older than the module requires.
+pub fn module(ctx: &Context) -> Option<String> {
+    let file = ctx.find_upwards(&project::files("go"))?;
+    let go_mod = parse_go_mod(&fs::read_to_string(file).ok()?);
+    let mut result = ICON.to_owned();
+    if let Some(module) = &go_mod.module {
+        result += module.rsplit('/').next().unwrap_or(module);
+    }
+    if let Some(required) = &go_mod.go {
+        result += &format!(" go{required}");
+        if config::bool("go.show_installed", false) {
+            if let Some(installed) = run_command_in(&ctx.path, "go", &["version"]).as_deref().and_then(parse_go_version) {
+                if version_cmp(&installed, required) == Ordering::Less {
+                    result += &format!("\x1b[31m<{installed}");
+                }
+            }
+        }
+    }
+    return Some(result + "\x1b[m");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("module github.com/kevna/tool\n\ngo 1.21\n", Some("github.com/kevna/tool"), Some("1.21"))]
+    #[case("module \"example.com/quoted\"\ngo 1.22.1\ntoolchain go1.22.3\n", Some("example.com/quoted"), Some("1.22.1"))]
+    #[case("module example.com/old\n", Some("example.com/old"), None)]
+    fn test_parse_go_mod(#[case] input: &str, #[case] module: Option<&str>, #[case] go: Option<&str>) {
+        let actual = parse_go_mod(input);
+        assert_eq!(module, actual.module.as_deref());
+        assert_eq!(go, actual.go.as_deref());
+    }
+
+    #[rstest]
+    #[case("go version go1.22.1 linux/amd64", Some("1.22.1"))]
+    #[case("go version devel", None)]
+    fn test_parse_go_version(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_go_version(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+}
@@ -0,0 +1,138 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::status::{cache, config};
+use super::{Context, cached_for, run_command};
+
+const MISSING: &str = "\x1b[31m\u{F0306}!\x1b[m";
+const LOCKED: &str = "\x1b[33m\u{F0306}\x1b[m";
+/// How long a key's lookup is trusted even though the keyrings haven't
+/// changed, as gpg may also be pointed elsewhere by its own config.
+const KEYGRIP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A git boolean, which may be spelled in any of several ways.
+fn is_true(value: &str) -> bool {
+    return ["true", "yes", "on", "1"].iter().any(|truth| value.eq_ignore_ascii_case(truth));
+}
+
+fn gnupg_home() -> Option<PathBuf> {
+    return env::var_os("GNUPGHOME").map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".gnupg")));
+}
+
+/// The keygrips able to sign for `key`, or for the default key, joined by
+/// commas and empty when there are none. Looked up again when a keyring
+/// changes, or after a minute.
+fn keygrips(key: Option<&str>) -> Option<String> {
+    let home = gnupg_home().unwrap_or_default();
+    let keyrings: Vec<String> = ["pubring.kbx", "pubring.gpg", "private-keys-v1.d"].iter()
+        .map(|file| cache::mtime(&home.join(file)).unwrap_or_default())
+        .collect();
+    let cache_key = format!("gpg:{}:{}:{}", home.display(), key.unwrap_or_default(), keyrings.join(","));
+    return cached_for(&cache_key, Some(KEYGRIP_INTERVAL), || {
+        let mut args = vec!["--list-secret-keys", "--with-colons", "--with-keygrip"];
+        args.extend(key);
+        let listing = run_command("gpg", &args).unwrap_or_default();
+        return Some(signing_keygrips(&listing).join(","));
+    });
+}
+
+/// Keygrips of the key's signing-capable (sub)keys from `gpg --with-colons --with-keygrip`.
+fn signing_keygrips(listing: &str) -> Vec<String> {
+    let mut result = vec![];
+    let mut signing = false;
+    for line in listing.lines() {
+        let fields: Vec<&str> = line.split(':').collect();
+        match fields[0] {
+            "sec" | "ssb" => signing = fields.get(11).is_some_and(|caps| caps.contains('s')),
+            "grp" if signing => result.extend(fields.get(9).map(|grip| grip.to_string())),
+            _ => {},
+        }
+    }
+    return result;
+}
+
+/// Whether gpg-agent holds the passphrase for any of the keygrips, from the
+/// seventh field of `KEYINFO --list` lines.
+fn agent_unlocked(keyinfo: &str, keygrips: &[String]) -> bool {
+    return keyinfo.lines().any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        return fields.len() > 6 && fields[1] == "KEYINFO"
+            && keygrips.iter().any(|grip| grip == fields[2]) && fields[6] == "1";
+    });
+}
+
+/// Warn when `gpg.enabled` is set and commits are to be signed but the key
+/// is missing (red) or the agent would have to prompt for the passphrase
+/// (yellow).
+pub fn signing(ctx: &Context) -> Option<String> {
+    if !config::bool("gpg.enabled", false) || !is_true(ctx.repo().config("commit.gpgsign")?) {
+        return None;
+    }
+    let key = ctx.repo().config("user.signingkey");
+    if ctx.repo().config("gpg.format") == Some("ssh") {
+        let key = key?;
+        if key.starts_with("key::") || Path::new(key).is_file() {
+            return None;
+        }
+        return Some(MISSING.to_owned());
+    }
+    let keygrips: Vec<String> = keygrips(key)?.split(',')
+        .filter(|grip| !grip.is_empty())
+        .map(str::to_owned)
+        .collect();
+    if keygrips.is_empty() {
+        return Some(MISSING.to_owned());
+    }
+    let keyinfo = run_command("gpg-connect-agent", &["KEYINFO --list", "/bye"]).unwrap_or_default();
+    if agent_unlocked(&keyinfo, &keygrips) {
+        return None;
+    }
+    return Some(LOCKED.to_owned());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const LISTING: &str = "\
+sec:u:255:22:AAAA:1700000000:::u:::scESC:::+:::23::0:
+fpr:::::::::1111:
+grp:::::::::GRIPPRIMARY:
+ssb:u:255:18:BBBB:1700000000::::::e:::+:::23:
+fpr:::::::::2222:
+grp:::::::::GRIPENCRYPT:
+ssb:u:255:22:CCCC:1700000000::::::s:::+:::23:
+fpr:::::::::3333:
+grp:::::::::GRIPSIGN:
+";
+
+    #[test]
+    fn test_signing_keygrips() {
+        let actual = signing_keygrips(LISTING);
+        assert_eq!(vec!["GRIPPRIMARY", "GRIPSIGN"], actual)
+    }
+
+    #[rstest]
+    #[case("true", true)]
+    #[case("Yes", true)]
+    #[case("1", true)]
+    #[case("false", false)]
+    #[case("", false)]
+    fn test_is_true(#[case] value: &str, #[case] expected: bool) {
+        let actual = is_true(value);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("S KEYINFO GRIPSIGN D - - 1 P - - -\nOK\n", true)]
+    #[case("S KEYINFO GRIPSIGN D - - - P - - -\nOK\n", false)]
+    #[case("S KEYINFO GRIPENCRYPT D - - 1 P - - -\nOK\n", false)]
+    fn test_agent_unlocked(#[case] input: &str, #[case] expected: bool) {
+        let keygrips = vec!["GRIPPRIMARY".to_owned(), "GRIPSIGN".to_owned()];
+        let actual = agent_unlocked(input, &keygrips);
+        assert_eq!(expected, actual)
+    }
+}
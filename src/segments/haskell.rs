@@ -0,0 +1,61 @@
+use std::fs;
+use std::sync::LazyLock;
+use regex::Regex;
+
+use super::{Context, cached_output, project};
+
+const ICON: &str = "\x1b[35m\u{E777}";
+
+static STACK_RESOLVER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^(?:resolver|snapshot):\s*(\S+)").unwrap());
+
+/// Read the `resolver:`/`snapshot:` of a stack.yaml, e.g. `lts-22.7` or `ghc-9.6.4`.
+fn parse_stack_yaml(contents: &str) -> Option<String> {
+    return STACK_RESOLVER.captures(contents).map(|caps| caps[1].trim_matches(['"', '\'']).to_owned());
+}
+
+static CABAL_COMPILER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^with-compiler:\s*ghc-?(\S+)").unwrap());
+
+/// Read `with-compiler: ghc-9.6.4` from a cabal.project.
+fn parse_cabal_project(contents: &str) -> Option<String> {
+    return CABAL_COMPILER.captures(contents).map(|caps| caps[1].to_owned());
+}
+
+/// Show the stack resolver for stack projects, or the GHC version for cabal
+/// projects, asking `ghc --numeric-version` (cached) when it isn't pinned.
+pub fn toolchain(ctx: &Context) -> Option<String> {
+    let file = ctx.find_upwards(&project::files("haskell"))?;
+    let contents = fs::read_to_string(&file).unwrap_or_default();
+    let version = if file.ends_with("stack.yaml") {
+        parse_stack_yaml(&contents)
+    } else {
+        parse_cabal_project(&contents).map(|version| format!("ghc{version}"))
+    };
+    let version = match version {
+        Some(version) => version,
+        None => format!("ghc{}", cached_output("ghc", &["--numeric-version"])?),
+    };
+    return Some(format!("{ICON}{version}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("resolver: lts-22.7\npackages:\n- .\n", Some("lts-22.7"))]
+    #[case("snapshot: 'nightly-2024-01-20'\n", Some("nightly-2024-01-20"))]
+    #[case("packages:\n- .\n", None)]
+    fn test_parse_stack_yaml(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_stack_yaml(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("packages: .\nwith-compiler: ghc-9.6.4\n", Some("9.6.4"))]
+    #[case("packages: .\n", None)]
+    fn test_parse_cabal_project(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_cabal_project(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+}
@@ -0,0 +1,10 @@
+use crate::status::config;
+use super::Context;
+
+/// Show the history event number passed with `--history` when `history.enabled` is set.
+pub fn event(ctx: &Context) -> Option<String> {
+    if !config::bool("history.enabled", false) {
+        return None;
+    }
+    return Some(format!("\x1b[90m!{}\x1b[m", ctx.options.history?));
+}
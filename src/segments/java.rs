@@ -0,0 +1,102 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+use regex::Regex;
+
+use super::{Context, project, tools};
+
+const ICON: &str = "\x1b[38;5;166m\u{E738}";
+
+static RELEASE_VERSION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?m)^JAVA_VERSION="([^"]+)""#).unwrap());
+
+/// Read `JAVA_VERSION="17.0.8"` from a JDK's `release` file.
+fn parse_release(contents: &str) -> Option<String> {
+    return RELEASE_VERSION.captures(contents).map(|caps| caps[1].to_owned());
+}
+
+/// Read `java=17.0.8-tem` from an `.sdkmanrc`.
+fn parse_sdkmanrc(contents: &str) -> Option<String> {
+    let line = contents.lines().find_map(|line| line.trim().strip_prefix("java="))?;
+    return Some(line.trim().to_owned());
+}
+
+static MAJOR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(\d+)(?:\.(\d+))?").unwrap());
+
+/// The major release of a version, mapping the legacy `1.8.0_382` scheme to `8`.
+fn major(version: &str) -> Option<u32> {
+    let caps = MAJOR.captures(version)?;
+    let first = caps[1].parse().ok()?;
+    if first == 1 {
+        return caps.get(2)?.as_str().parse().ok();
+    }
+    return Some(first);
+}
+
+/// The JDK pinned by `.java-version` (jenv), `.sdkmanrc` or `.tool-versions`, closest first.
+fn pinned_version(path: &Path) -> Option<String> {
+    for dir in path.ancestors() {
+        if let Ok(contents) = fs::read_to_string(dir.join(".java-version")) {
+            return Some(contents.trim().to_owned());
+        }
+        if let Some(version) = fs::read_to_string(dir.join(".sdkmanrc")).ok().as_deref().and_then(parse_sdkmanrc) {
+            return Some(version);
+        }
+        if let Some(version) = tools::tool_version(dir, "java") {
+            return Some(version);
+        }
+    }
+    return None;
+}
+
+fn active_version() -> Option<String> {
+    let home = env::var_os("JAVA_HOME")?;
+    return parse_release(&fs::read_to_string(Path::new(&home).join("release")).ok()?);
+}
+
+/// Show the JDK in JVM projects, in red when `$JAVA_HOME` isn't the pinned major release.
+pub fn version(ctx: &Context) -> Option<String> {
+    ctx.find_upwards(&project::files("java"))?;
+    let pinned = pinned_version(&ctx.path);
+    let active = active_version();
+    return match (active, pinned) {
+        (Some(active), Some(pinned)) if major(&active) != major(&pinned) => {
+            Some(format!("{ICON}\x1b[91m{active}\x1b[m"))
+        },
+        (Some(version), _) | (None, Some(version)) => Some(format!("{ICON}{version}\x1b[m")),
+        (None, None) => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"17.0.8\"\n", Some("17.0.8"))]
+    #[case("IMPLEMENTOR=\"Oracle\"\n", None)]
+    fn test_parse_release(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_release(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("# sdkman\njava=21.0.1-tem\n", Some("21.0.1-tem"))]
+    #[case("gradle=8.4\n", None)]
+    fn test_parse_sdkmanrc(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_sdkmanrc(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("17.0.8", Some(17))]
+    #[case("1.8.0_382", Some(8))]
+    #[case("temurin-21.0.1+12", Some(21))]
+    #[case("21", Some(21))]
+    #[case("openjdk", None)]
+    fn test_major(#[case] input: &str, #[case] expected: Option<u32>) {
+        let actual = major(input);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,10 @@
+use crate::status::config;
+use super::Context;
+
+/// How many jobs the shell has in the background, passed with `--jobs`,
+/// as `jobs.symbol` (`✦` by default) and the count.
+pub fn background(ctx: &Context) -> Option<String> {
+    let jobs = ctx.options.jobs.filter(|jobs| *jobs > 0)?;
+    let symbol = config::string("jobs.symbol").unwrap_or("\u{2726}");
+    return Some(format!("\x1b[34m{symbol}{jobs}\x1b[m"));
+}
@@ -0,0 +1,31 @@
+use super::Context;
+
+/// Show the zsh vi-mode keymap passed with `--keymap`.
+pub fn vi_mode(ctx: &Context) -> Option<String> {
+    let indicator = match ctx.options.keymap.as_deref()? {
+        "vicmd" => "\x1b[30;43m NORMAL ",
+        "visual" => "\x1b[30;45m VISUAL ",
+        "viins" | "main" => "\x1b[30;42m INSERT ",
+        _ => return None,
+    };
+    return Some(format!("{indicator}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use rstest::rstest;
+    use crate::status::{Options, style::{Format, StyledText}};
+
+    #[rstest]
+    #[case("vicmd", Format::Ansi, "\x1b[30;43m NORMAL \x1b[m")]
+    #[case("vicmd", Format::Tmux, "#[fg=colour0,bg=colour3] NORMAL #[default]")]
+    #[case("main", Format::Tmux, "#[fg=colour0,bg=colour2] INSERT #[default]")]
+    fn test_vi_mode_background(#[case] keymap: &str, #[case] format: Format, #[case] expected: &str) {
+        let options = Options{keymap: Some(keymap.to_owned()), ..Options::default()};
+        let ctx = Context::new(PathBuf::from("/"), options);
+        let actual = StyledText::from_ansi(&vi_mode(&ctx).unwrap()).render(format);
+        assert_eq!(expected, actual)
+    }
+}
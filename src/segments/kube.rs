@@ -0,0 +1,100 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::status::{cache, config};
+use super::Context;
+
+const ICON: &str = "\x1b[34m\u{2388} ";
+
+/// Kubeconfig files in precedence order, from `$KUBECONFIG` or `~/.kube/config`.
+fn kubeconfig_files() -> Vec<PathBuf> {
+    if let Some(files) = env::var_os("KUBECONFIG") {
+        return env::split_paths(&files).filter(|file| !file.as_os_str().is_empty()).collect();
+    }
+    return env::var_os("HOME").map(|home| Path::new(&home).join(".kube/config")).into_iter().collect();
+}
+
+/// Resolve the current context and its namespace across the merged kubeconfig
+/// documents; the first document setting a value wins, as with kubectl.
+fn current_context(documents: &[Yaml]) -> Option<(String, Option<String>)> {
+    let context = documents.iter()
+        .find_map(|document| document["current-context"].as_str())
+        .filter(|context| !context.is_empty())?;
+    let namespace = documents.iter()
+        .filter_map(|document| document["contexts"].as_vec())
+        .flatten()
+        .find(|entry| entry["name"].as_str() == Some(context))
+        .and_then(|entry| entry["context"]["namespace"].as_str());
+    return Some((context.to_owned(), namespace.map(str::to_owned)));
+}
+
+/// With `kube.detect_files` configured, only show the segment in projects
+/// containing one of those files (e.g. `Chart.yaml`, `kustomization.yaml`).
+fn detected(ctx: &Context) -> bool {
+    let files = config::strings("kube.detect_files");
+    return files.is_empty() || ctx.find_upwards(&files).is_some();
+}
+
+/// The current context as `context/namespace`, or empty without one.
+fn parse_kubeconfig(files: &[PathBuf]) -> String {
+    let mut documents = vec![];
+    for file in files {
+        if let Ok(contents) = fs::read_to_string(file) {
+            documents.extend(YamlLoader::load_from_str(&contents).unwrap_or_default());
+        }
+    }
+    return match current_context(&documents) {
+        Some((context, Some(namespace))) => format!("{context}/{namespace}"),
+        Some((context, None)) => context,
+        None => String::new(),
+    };
+}
+
+/// Show the current kubectl context and namespace, e.g. `⎈ prod-eu/payments`,
+/// when `kube.enabled` is set. The kubeconfig is parsed again only when one
+/// of its files changes.
+pub fn context(ctx: &Context) -> Option<String> {
+    if !config::bool("kube.enabled", false) || !detected(ctx) {
+        return None;
+    }
+    let files = kubeconfig_files();
+    let fingerprint: Vec<String> = files.iter()
+        .map(|file| format!("{}:{}", file.display(), cache::mtime(file).unwrap_or_default()))
+        .collect();
+    let current = cache::get_or_insert("kube", &fingerprint.join(","), || Some(parse_kubeconfig(&files)))?;
+    return Some(format!("{ICON}{current}\x1b[m")).filter(|_| !current.is_empty());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const CONFIG: &str = "
+apiVersion: v1
+contexts:
+- context:
+    cluster: eu
+    namespace: payments
+  name: prod-eu
+- context:
+    cluster: local
+  name: kind
+current-context: prod-eu
+";
+
+    #[rstest]
+    #[case(&[CONFIG], Some(("prod-eu", Some("payments"))))]
+    #[case(&["current-context: kind\n", CONFIG], Some(("kind", None)))]
+    #[case(&["current-context: \"\"\n"], None)]
+    #[case(&["apiVersion: v1\n"], None)]
+    fn test_current_context(#[case] input: &[&str], #[case] expected: Option<(&str, Option<&str>)>) {
+        let documents: Vec<Yaml> = input.iter()
+            .flat_map(|contents| YamlLoader::load_from_str(contents).unwrap())
+            .collect();
+        let actual = current_context(&documents);
+        assert_eq!(expected, actual.as_ref().map(|(context, namespace)| (context.as_str(), namespace.as_deref())))
+    }
+}
@@ -0,0 +1,51 @@
+use std::fs;
+use std::thread;
+
+use crate::status::config;
+use super::{Context, run_command};
+
+const ICON: &str = "\u{F0E4E}";
+
+/// The 1-minute average from `/proc/loadavg` or macOS `sysctl -n vm.loadavg` (`{ 1.23 1.10 1.05 }`).
+fn parse_loadavg(contents: &str) -> Option<f64> {
+    let word = contents.split_whitespace().find(|word| *word != "{")?;
+    return word.parse().ok();
+}
+
+fn loadavg() -> Option<f64> {
+    if let Ok(contents) = fs::read_to_string("/proc/loadavg") {
+        return parse_loadavg(&contents);
+    }
+    return parse_loadavg(&run_command("sysctl", &["-n", "vm.loadavg"])?);
+}
+
+/// Show the load average when `load.enabled` is set and the load per CPU
+/// reaches `load.threshold`, turning red once every CPU is busy.
+pub fn average(_ctx: &Context) -> Option<String> {
+    if !config::bool("load.enabled", false) {
+        return None;
+    }
+    let load = loadavg()?;
+    let cpus = thread::available_parallelism().map_or(1, usize::from) as f64;
+    let ratio = load / cpus;
+    if ratio < config::float("load.threshold", 0.7) {
+        return None;
+    }
+    let color = if ratio >= 1.0 { "\x1b[31m" } else { "\x1b[33m" };
+    return Some(format!("{color}{ICON}{load:.2}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("0.52 0.58 0.59 2/1234 56789\n", Some(0.52))]
+    #[case("{ 1.23 1.10 1.05 }", Some(1.23))]
+    #[case("", None)]
+    fn test_parse_loadavg(#[case] input: &str, #[case] expected: Option<f64>) {
+        let actual = parse_loadavg(input);
+        assert_eq!(expected, actual)
+    }
+}
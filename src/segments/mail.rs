@@ -0,0 +1,60 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::status::config;
+use super::Context;
+
+const ICON: &str = "\x1b[33m\u{F01F0}";
+
+/// An mbox has unread mail when it was written to after it was last read,
+/// the same test shells use for `$MAILCHECK`.
+fn mbox_unread(size: u64, mtime: i64, atime: i64) -> bool {
+    return size > 0 && mtime > atime;
+}
+
+/// Number of unread messages: the entries in a Maildir's `new`, or one
+/// for a freshly written mbox.
+fn unread(path: &Path) -> Option<usize> {
+    let new = path.join("new");
+    if new.is_dir() {
+        return Some(fs::read_dir(new).ok()?.count());
+    }
+    let meta = fs::metadata(path).ok()?;
+    let seconds = |time: SystemTime| time.duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs() as i64);
+    return Some(mbox_unread(meta.len(), seconds(meta.modified().ok()?), seconds(meta.accessed().ok()?)) as usize);
+}
+
+/// Show an envelope when the mailbox at `mail.path` or `$MAIL` has unread
+/// mail, if `mail.enabled` is set.
+pub fn unread_mail(_ctx: &Context) -> Option<String> {
+    if !config::bool("mail.enabled", false) {
+        return None;
+    }
+    let path = match config::string("mail.path") {
+        Some(path) => path.into(),
+        None => env::var_os("MAIL")?,
+    };
+    return match unread(Path::new(&path))? {
+        0 => None,
+        1 => Some(format!("{ICON}\x1b[m")),
+        count => Some(format!("{ICON} {count}\x1b[m")),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0, 20, 10, false)]
+    #[case(512, 20, 10, true)]
+    #[case(512, 10, 20, false)]
+    #[case(512, 10, 10, false)]
+    fn test_mbox_unread(#[case] size: u64, #[case] mtime: i64, #[case] atime: i64, #[case] expected: bool) {
+        let actual = mbox_unread(size, mtime, atime);
+        assert_eq!(expected, actual)
+    }
+}
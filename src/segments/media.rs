@@ -0,0 +1,66 @@
+use crate::status::config;
+use super::{printable, run_command, Context};
+
+const ICON: &str = "\u{F075A} ";
+
+/// Shorten `text` to at most `max` characters, ending in `…` when cut.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_owned();
+    }
+    let kept: String = text.chars().take(max.saturating_sub(1)).collect();
+    return kept.trim_end().to_owned() + "\u{2026}";
+}
+
+/// Split playerctl's `status<TAB>artist - title` output. Browsers take
+/// titles from web pages, so control characters are dropped from them.
+fn parse_metadata(output: &str) -> Option<(&str, String)> {
+    let (status, track) = output.split_once('\t')?;
+    // a missing artist or title leaves a dangling separator
+    let track = printable(track.trim().trim_matches('-').trim());
+    if track.is_empty() {
+        return None;
+    }
+    return Some((status, track));
+}
+
+/// Show the track of the active MPRIS player, via playerctl, when
+/// `media.enabled` is set; paused tracks are dimmed.
+pub fn now_playing(_ctx: &Context) -> Option<String> {
+    if !config::bool("media.enabled", false) {
+        return None;
+    }
+    let output = run_command("playerctl", &["metadata", "--format", "{{status}}\t{{artist}} - {{title}}"])?;
+    let (status, track) = parse_metadata(&output)?;
+    let track = truncate(&track, usize::try_from(config::integer("media.max_length", 30)).unwrap_or(30));
+    return match status {
+        "Playing" => Some(format!("\x1b[35m{ICON}{track}\x1b[m")),
+        "Paused" => Some(format!("\x1b[90m{ICON}{track}\x1b[m")),
+        _ => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("short", 10, "short")]
+    #[case("Boards of Canada - Roygbiv", 12, "Boards of C\u{2026}")]
+    #[case("日本語のタイトル", 4, "日本語\u{2026}")]
+    fn test_truncate(#[case] input: &str, #[case] max: usize, #[case] expected: &str) {
+        let actual = truncate(input, max);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("Playing\tBoards of Canada - Roygbiv", Some(("Playing", "Boards of Canada - Roygbiv")))]
+    #[case("Paused\t - Some Stream", Some(("Paused", "Some Stream")))]
+    #[case("Stopped\t - ", None)]
+    #[case("Playing\tEvil \x1b]0;owned\x07Page - YouTube", Some(("Playing", "Evil ]0;ownedPage - YouTube")))]
+    fn test_parse_metadata(#[case] input: &str, #[case] expected: Option<(&str, &str)>) {
+        let actual = parse_metadata(input);
+        assert_eq!(expected.map(|(status, track)| (status, track.to_owned())), actual)
+    }
+}
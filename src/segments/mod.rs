@@ -0,0 +1,801 @@
+pub mod aws;
+pub mod battery;
+pub mod cargo;
+pub mod character;
+pub mod container;
+pub mod custom;
+pub mod disk;
+pub mod dotnet;
+pub mod duration;
+#[cfg(feature = "plugins")]
+pub mod dylib;
+pub mod elixir;
+pub mod envvar;
+pub mod golang;
+pub mod gpg;
+pub mod haskell;
+pub mod history;
+pub mod java;
+pub mod jobs;
+pub mod keymap;
+pub mod kube;
+pub mod load;
+pub mod mail;
+pub mod media;
+pub mod netfs;
+pub mod network;
+pub mod nix;
+pub mod node;
+pub mod os;
+pub mod project;
+pub mod plugin;
+pub mod proxy;
+pub mod python;
+pub mod ruby;
+#[cfg(feature = "tokio")]
+mod runtime;
+pub mod rust;
+pub mod screen;
+pub mod shlvl;
+pub mod ssh;
+pub mod tmux;
+pub mod todo;
+pub mod tools;
+pub mod vault;
+pub mod vpn;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod weather;
+pub mod writable;
+pub mod wsl;
+pub mod zig;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::status::{cache, config, git, log, Options};
+use crate::status::style::{Color, Format, Style};
+pub use crate::status::style::StyledText;
+
+/// Everything a segment may base its output on. Clones share the file
+/// cache and the repository status.
+#[derive(Clone)]
+pub struct Context {
+    pub path: PathBuf,
+    pub options: Options,
+    /// Which candidate files exist, so segments probing for the same
+    /// project markers don't repeat the lookups.
+    files: Arc<Mutex<HashMap<PathBuf, bool>>>,
+    repo: Arc<git::GitRepo>,
+}
+
+impl Context {
+    pub fn new(path: PathBuf, options: Options) -> Context {
+        let repo = Arc::new(git::GitRepo::new(&path));
+        return Context{
+            path,
+            options,
+            files: Arc::new(Mutex::new(HashMap::new())),
+            repo,
+        };
+    }
+
+    /// The repository containing the path, shared by every segment of
+    /// the render so none of them repeats a git command another ran.
+    pub fn repo(&self) -> &git::GitRepo {
+        return &self.repo;
+    }
+
+    /// The status of the repository containing the path, gathered once
+    /// however many segments ask, from the stale cache when it's enabled.
+    pub fn git(&self) -> Option<&git::Summary> {
+        return self.repo.summary();
+    }
+
+    /// The repositories the one containing the path is nested in,
+    /// outermost first, as for a submodule.
+    pub fn enclosing_git(&self) -> &[git::Summary] {
+        return self.repo.enclosing();
+    }
+
+    /// The repository status if a segment already asked for it.
+    pub fn gathered_git(&self) -> Option<&git::Summary> {
+        return self.repo.gathered();
+    }
+
+    pub fn is_file(&self, file: &Path) -> bool {
+        let mut files = self.files.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        return *files.entry(file.to_path_buf()).or_insert_with(|| file.is_file());
+    }
+
+    /// Find the closest file with one of the given names in the cwd or its parents.
+    pub fn find_upwards(&self, names: &[&str]) -> Option<PathBuf> {
+        return self.find_upwards_from(&self.path, names);
+    }
+
+    /// Find the closest file with one of the given names in `path` or its parents.
+    pub fn find_upwards_from(&self, path: &Path, names: &[&str]) -> Option<PathBuf> {
+        for dir in path.ancestors() {
+            for name in names {
+                let candidate = dir.join(name);
+                if self.is_file(&candidate) {
+                    return Some(candidate);
+                }
+            }
+        }
+        return None;
+    }
+}
+
+/// A piece of the statusline, looked up by name in a `Registry`.
+pub trait Segment: Send + Sync {
+    fn name(&self) -> &str;
+    fn render(&self, ctx: &Context) -> Option<StyledText>;
+
+    /// Slow segments are drawn as a placeholder in the first phase of an
+    /// async prompt and only rendered for real in the second.
+    fn slow(&self) -> bool {
+        return false;
+    }
+}
+
+/// A segment implemented by one of the plain functions in this module.
+#[derive(Clone, Copy)]
+struct Builtin {
+    name: &'static str,
+    render: fn(&Context) -> Option<String>,
+}
+
+impl Segment for Builtin {
+    fn name(&self) -> &str {
+        return self.name;
+    }
+
+    fn render(&self, ctx: &Context) -> Option<StyledText> {
+        return (self.render)(ctx).map(StyledText::from);
+    }
+
+    /// Those in `async.segments`, or the ones running arbitrary commands by default.
+    fn slow(&self) -> bool {
+        let slow = config::strings("async.segments");
+        if slow.is_empty() {
+            return DEFAULT_SLOW.contains(&self.name);
+        }
+        return slow.contains(&self.name);
+    }
+}
+
+const DEFAULT_SLOW: &[&str] = &["custom", "todo", "vault"];
+
+/// Segments rendered after the path, in display order.
+const SEGMENTS: &[Builtin] = &[
+    Builtin{name: "rust", render: rust::toolchain},
+    Builtin{name: "cargo", render: cargo::package},
+    Builtin{name: "node", render: node::package},
+    Builtin{name: "golang", render: golang::module},
+    Builtin{name: "pyenv", render: python::pyenv},
+    Builtin{name: "python", render: python::environment},
+    Builtin{name: "ruby", render: ruby::version},
+    Builtin{name: "java", render: java::version},
+    Builtin{name: "elixir", render: elixir::version},
+    Builtin{name: "zig", render: zig::version},
+    Builtin{name: "haskell", render: haskell::toolchain},
+    Builtin{name: "dotnet", render: dotnet::sdk},
+    Builtin{name: "kube", render: kube::context},
+    Builtin{name: "aws", render: aws::profile},
+    Builtin{name: "vault", render: vault::token},
+    Builtin{name: "gpg", render: gpg::signing},
+    Builtin{name: "ssh", render: ssh::agent},
+    Builtin{name: "network", render: network::status},
+    Builtin{name: "vpn", render: vpn::indicator},
+    Builtin{name: "weather", render: weather::forecast},
+    Builtin{name: "media", render: media::now_playing},
+    Builtin{name: "mail", render: mail::unread_mail},
+    Builtin{name: "custom", render: custom::commands},
+    Builtin{name: "envvar", render: envvar::variables},
+    Builtin{name: "container", render: container::indicator},
+    Builtin{name: "nix", render: nix::shell},
+    Builtin{name: "battery", render: battery::status},
+    Builtin{name: "load", render: load::average},
+    Builtin{name: "disk", render: disk::free},
+    Builtin{name: "netfs", render: netfs::indicator},
+    Builtin{name: "writable", render: writable::indicator},
+    Builtin{name: "shlvl", render: shlvl::depth},
+    Builtin{name: "os", render: os::logo},
+    Builtin{name: "wsl", render: wsl::indicator},
+    Builtin{name: "proxy", render: proxy::indicator},
+    Builtin{name: "tmux", render: tmux::session},
+    Builtin{name: "screen", render: screen::session},
+    Builtin{name: "project", render: project::icons},
+    Builtin{name: "tools", render: tools::pinned},
+    Builtin{name: "todo", render: todo::markers},
+    Builtin{name: "keymap", render: keymap::vi_mode},
+    Builtin{name: "history", render: history::event},
+    Builtin{name: "duration", render: duration::elapsed},
+    Builtin{name: "jobs", render: jobs::background},
+    Builtin{name: "character", render: character::prompt},
+];
+
+/// Match `text` against a glob where `*` matches any run of characters and `?` any one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    return pattern[p..].iter().all(|&c| c == '*');
+}
+
+/// Turn a color name (`red`, `bright-blue`) or 256-color number into an escape sequence.
+pub fn color(name: &str) -> Option<String> {
+    return Color::parse(name).map(|color| Style::fg(color).prefix());
+}
+
+/// Find an executable on `$PATH`.
+pub fn which(program: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    let found = cache::get_or_insert(&format!("which:{program}"), &path_fingerprint(&path), || {
+        let found = env::split_paths(&path)
+            .flat_map(|dir| [dir.join(program), dir.join(format!("{program}{}", env::consts::EXE_SUFFIX))])
+            .find(|candidate| candidate.is_file());
+        return Some(found.map(|found| found.to_string_lossy().into_owned()).unwrap_or_default());
+    })?;
+    if found.is_empty() {
+        return None;
+    }
+    return Some(PathBuf::from(found));
+}
+
+/// Changes whenever PATH or one of its directories does, i.e. whenever a
+/// program may have been installed or removed.
+pub fn path_fingerprint(path: &OsStr) -> String {
+    static LAST: Mutex<Option<(OsString, String)>> = Mutex::new(None);
+    let mut last = LAST.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some((previous, fingerprint)) = last.as_ref() {
+        if previous == path {
+            return fingerprint.to_owned();
+        }
+    }
+    let mut fingerprint = path.to_string_lossy().into_owned();
+    for dir in env::split_paths(path) {
+        fingerprint += &cache::mtime(&dir).unwrap_or_default();
+    }
+    let fingerprint = cache::digest(&fingerprint);
+    *last = Some((path.to_owned(), fingerprint.to_owned()));
+    return fingerprint;
+}
+
+/// Whether `program` is on PATH, checked once per change to PATH.
+pub fn installed(program: &str) -> bool {
+    return which(program).is_some();
+}
+
+/// `text` without control characters, for text from the network or other
+/// programs that could otherwise move the cursor or retitle the terminal.
+pub fn printable(text: &str) -> String {
+    return text.chars().filter(|c| !c.is_control()).collect();
+}
+
+/// Run a program and return its trimmed stdout, or None if it failed.
+pub fn run_command(program: &str, args: &[&str]) -> Option<String> {
+    return stdout(Command::new(program), program, args);
+}
+
+/// Like `run_command`, run in `dir`. A directory can be named in bytes that
+/// aren't UTF-8, which `-C <dir>` style arguments can't pass on.
+pub fn run_command_in(dir: &Path, program: &str, args: &[&str]) -> Option<String> {
+    let mut command = Command::new(program);
+    command.current_dir(dir);
+    return stdout(command, program, args);
+}
+
+/// Like `run_command`, with `vars` added to the program's environment,
+/// returning what it printed whether or not it succeeded, for telling
+/// failures apart. None when it couldn't be run at all.
+pub fn run_command_output(program: &str, args: &[&str], vars: &[(&str, &str)]) -> Option<std::process::Output> {
+    let mut command = Command::new(program);
+    command.envs(vars.iter().copied());
+    return run(command, program, args);
+}
+
+fn run(mut command: Command, program: &str, args: &[&str]) -> Option<std::process::Output> {
+    if !program.contains('/') && !installed(program) {
+        log::debug(|| format!("skipped `{program} {}`: {program} isn't installed", args.join(" ")));
+        return None;
+    }
+    let started = Instant::now();
+    command.args(args);
+    let output = output(command);
+    log::command(program, args, started.elapsed(), output.as_ref().is_ok_and(|output| output.status.success()));
+    return output.ok();
+}
+
+fn stdout(command: Command, program: &str, args: &[&str]) -> Option<String> {
+    let output = run(command, program, args)?;
+    if !output.status.success() {
+        return None;
+    }
+    return Some(String::from_utf8_lossy(&output.stdout).trim_end().to_string());
+}
+
+#[cfg(feature = "tokio")]
+pub use runtime::output;
+
+/// Run a command to completion.
+#[cfg(not(feature = "tokio"))]
+pub fn output(mut command: Command) -> std::io::Result<std::process::Output> {
+    return command.output();
+}
+
+/// Compare dotted version strings numerically, so `1.9` sorts before `1.10`.
+/// Non-numeric suffixes such as `-rc1` are ignored.
+pub fn version_cmp(a: &str, b: &str) -> Ordering {
+    let parse = |version: &str| -> Vec<u64> {
+        return version.split('.')
+            .map(|part| part.chars().take_while(char::is_ascii_digit).collect::<String>())
+            .map(|digits| digits.parse().unwrap_or(0))
+            .collect();
+    };
+    return parse(a).cmp(&parse(b));
+}
+
+/// Run a command, killing it if it hasn't finished within `timeout`.
+pub fn run_with_timeout(command: Command, timeout: Duration) -> Option<String> {
+    return run_with_input(command, None, timeout);
+}
+
+/// Like `run_with_timeout`, feeding `input` to the command's stdin.
+pub fn run_with_input(mut command: Command, input: Option<Vec<u8>>, timeout: Duration) -> Option<String> {
+    let started = Instant::now();
+    let program = command.get_program().to_string_lossy().into_owned();
+    let args: Vec<String> = command.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let stdin = if input.is_some() { Stdio::piped() } else { Stdio::null() };
+    let mut child = command
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        thread::spawn(move || stdin.write_all(&input));
+    }
+    // drain stdout on a thread so a chatty child can't block on a full pipe
+    let mut stdout = child.stdout.take()?;
+    let reader = thread::spawn(move || {
+        let mut output = vec![];
+        let _ = stdout.read_to_end(&mut output);
+        return output;
+    });
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => break,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(5)),
+            Ok(None) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                log::debug(|| format!("killed `{program} {}` after {timeout:?}", args.join(" ")));
+                return None;
+            },
+            _ => {
+                log::command(&program, &args, started.elapsed(), false);
+                return None;
+            },
+        }
+    }
+    log::command(&program, &args, started.elapsed(), true);
+    let output = reader.join().ok()?;
+    return Some(String::from_utf8_lossy(&output).trim_end().to_string());
+}
+
+/// Reuse a computed value for `interval` before computing it again, or
+/// compute it every time without one.
+pub fn cached_for(key: &str, interval: Option<Duration>, compute: impl FnOnce() -> Option<String>) -> Option<String> {
+    let Some(interval) = interval else {
+        return compute();
+    };
+    let bucket = cache::now() / interval.as_secs().max(1);
+    return cache::get_or_insert(key, &bucket.to_string(), compute);
+}
+
+/// Like `run_command`, but cached until the executable itself changes, for
+/// version queries of slow-starting toolchains.
+pub fn cached_output(program: &str, args: &[&str]) -> Option<String> {
+    let executable = which(program)?;
+    let key = format!("{}:{}", executable.display(), args.join(" "));
+    let output = cache::get_or_insert(&key, &cache::mtime(&executable)?, || {
+        log::debug(|| format!("no cached output for `{program} {}`", args.join(" ")));
+        return run_command(program, args);
+    });
+    return output;
+}
+
+/// Background work started with `cache::refresh_in_background`.
+pub fn refresh(task: &str) {
+    match task {
+        "network" => network::refresh(),
+        "weather" => weather::refresh(),
+        _ => if let Some(root) = task.strip_prefix("git:") {
+            crate::status::git::refresh(root);
+        } else if let Some(dir) = task.strip_prefix("cd:") {
+            crate::status::git::precompute(Path::new(dir));
+        } else if let Some(segment) = task.strip_prefix("segment:") {
+            finish(segment);
+        },
+    }
+}
+
+/// Why a segment was or wasn't shown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reason {
+    Shown,
+    /// It had nothing to show here, e.g. outside any project it applies to.
+    Empty,
+    /// Listed in `segments.disabled`.
+    Disabled,
+    /// A slow segment left for the second phase of an async prompt.
+    Deferred,
+    /// It overran its deadline and was left behind.
+    TimedOut,
+    Panicked,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", match self {
+            Reason::Shown => "shown",
+            Reason::Empty => "nothing to show here",
+            Reason::Disabled => "listed in segments.disabled",
+            Reason::Deferred => "deferred by --async",
+            Reason::TimedOut => "timed out",
+            Reason::Panicked => "it panicked",
+        });
+    }
+}
+
+/// How long one segment took to render, and whether it showed anything.
+#[derive(Clone, Debug)]
+pub struct Timing {
+    pub name: String,
+    pub took: Duration,
+    pub shown: bool,
+    pub reason: Reason,
+}
+
+/// Segment renders still going, those abandoned at their deadline included.
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts a segment's render as going from before its thread is started
+/// until it's dropped, however the render ends.
+struct Running;
+
+impl Running {
+    fn start() -> Running {
+        RUNNING.fetch_add(1, AtomicOrdering::SeqCst);
+        return Running;
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        RUNNING.fetch_sub(1, AtomicOrdering::SeqCst);
+    }
+}
+
+/// How many segments are still rendering, as those that missed their
+/// deadline carry on after the statusline is drawn without them.
+pub fn running() -> usize {
+    return RUNNING.load(AtomicOrdering::SeqCst);
+}
+
+fn render_one(segment: &dyn Segment, ctx: &Context, placeholder: &str) -> (Reason, Option<StyledText>) {
+    if !segment.slow() {
+        return logged(segment.name(), segment.render(ctx));
+    }
+    let key = format!("async:{}:{}", segment.name(), ctx.path.display());
+    if ctx.options.deferred {
+        let shown = cache::read(&key).is_some_and(|(_, shown)| shown == "1");
+        log::debug(|| format!("segment {}: deferred, shown last time: {shown}", segment.name()));
+        return (Reason::Deferred, shown.then(|| StyledText::from_ansi(placeholder)));
+    }
+    let (reason, rendered) = logged(segment.name(), segment.render(ctx));
+    let shown = if rendered.is_some() { "1" } else { "0" };
+    if cache::read(&key).is_none_or(|(_, previous)| previous != shown) {
+        cache::write(&key, "", shown);
+    }
+    return (reason, rendered);
+}
+
+fn timeout(name: &str) -> Duration {
+    return config::value(&format!("segments.timeouts.{name}"))
+        .or_else(|| config::value("segments.timeout"))
+        .and_then(config::duration)
+        .unwrap_or(Duration::from_secs(1));
+}
+
+/// A segment rendering on its own thread.
+struct Pending {
+    name: String,
+    receiver: mpsc::Receiver<(Duration, Reason, Option<StyledText>)>,
+    /// When its time started: the prompt's start when rendering in
+    /// parallel, its own otherwise.
+    started: Instant,
+    deadline: Instant,
+}
+
+/// How often a segment that keeps overrunning is finished behind the
+/// prompt, in seconds.
+const FINISH_INTERVAL: u64 = 10;
+
+fn finished(name: String, took: Duration, reason: Reason, rendered: Option<StyledText>) -> (Timing, Option<StyledText>) {
+    let timing = Timing{name, took, shown: rendered.is_some(), reason};
+    return (timing, rendered);
+}
+
+/// A segment left behind at its deadline, having taken the `took` up to
+/// it. It's finished behind the prompt for `path`, so what it caches is
+/// there for the next one rather than it overrunning every time.
+fn timed_out(name: String, took: Duration, path: &Path) -> (Timing, Option<StyledText>) {
+    log::debug(|| format!("segment {name}: timed out"));
+    finish_in_background(&name, path);
+    let rendered = config::string("segments.timeout_placeholder").map(StyledText::from_ansi);
+    let timing = Timing{name, took, shown: rendered.is_some(), reason: Reason::TimedOut};
+    return (timing, rendered);
+}
+
+/// Render the builtin segment `name` for `path` behind the prompt, unless
+/// it was started in the last `FINISH_INTERVAL` seconds and may still be
+/// going.
+fn finish_in_background(name: &str, path: &Path) {
+    if Registry::named(name).is_none() {
+        return;
+    }
+    let task = format!("segment:{name}:{}", path.display());
+    let key = format!("finishing:{task}");
+    let started = cache::read(&key).and_then(|(started, _)| started.parse::<u64>().ok());
+    if started.is_some_and(|started| cache::now().saturating_sub(started) < FINISH_INTERVAL) {
+        return;
+    }
+    cache::write(&key, &cache::now().to_string(), "");
+    cache::refresh_in_background(&task);
+}
+
+/// Background half of `finish_in_background`, run as `__refresh
+/// segment:<name>:<dir>` without a deadline.
+fn finish(task: &str) {
+    let Some((name, dir)) = task.split_once(':') else {
+        return;
+    };
+    let Some(registry) = Registry::named(name) else {
+        return;
+    };
+    let ctx = Context::new(PathBuf::from(dir), Options::default());
+    for segment in &registry.segments {
+        segment.render(&ctx);
+    }
+}
+
+fn panicked(name: String) -> (Timing, Option<StyledText>) {
+    // a panicking segment only loses itself
+    log::debug(|| format!("segment {name}: hidden, it panicked"));
+    return (Timing{name, took: Duration::ZERO, shown: false, reason: Reason::Panicked}, None);
+}
+
+/// Wait for a segment's thread until its deadline.
+fn collect(pending: Pending, path: &Path) -> (Timing, Option<StyledText>) {
+    let name = pending.name;
+    let wait = pending.deadline.saturating_duration_since(Instant::now());
+    return match pending.receiver.recv_timeout(wait) {
+        Ok((took, reason, rendered)) => finished(name, took, reason, rendered),
+        Err(mpsc::RecvTimeoutError::Timeout) => timed_out(name, pending.deadline - pending.started, path),
+        Err(mpsc::RecvTimeoutError::Disconnected) => panicked(name),
+    };
+}
+
+fn logged(name: &str, rendered: Option<StyledText>) -> (Reason, Option<StyledText>) {
+    log::debug(|| match &rendered {
+        Some(text) => format!("segment {name}: shown {:?}", text.render(Format::Plain)),
+        None => format!("segment {name}: hidden, {}", Reason::Empty),
+    });
+    let reason = if rendered.is_some() { Reason::Shown } else { Reason::Empty };
+    return (reason, rendered);
+}
+
+/// The segments making up a statusline, in display order.
+#[derive(Default)]
+pub struct Registry {
+    segments: Vec<Arc<dyn Segment>>,
+}
+
+impl Registry {
+    /// All the segments shipped with statusline.
+    pub fn builtin() -> Registry {
+        let mut registry = Registry::default();
+        for segment in SEGMENTS {
+            registry.register(*segment);
+        }
+        return registry;
+    }
+
+    /// The builtin segment called `name`, on its own.
+    pub fn named(name: &str) -> Option<Registry> {
+        let segment = SEGMENTS.iter().find(|segment| segment.name == name)?;
+        let mut registry = Registry::default();
+        registry.register(*segment);
+        return Some(registry);
+    }
+
+    /// Add a segment after those already registered.
+    pub fn register(&mut self, segment: impl Segment + 'static) {
+        self.segments.push(Arc::new(segment));
+    }
+
+    /// Append all of `other`'s segments after these.
+    pub fn extend(&mut self, other: Registry) {
+        self.segments.extend(other.segments);
+    }
+
+    /// Put the segments in the order `order` names them, with `*` standing
+    /// for any not listed; without one those go last, in their old order.
+    pub fn reorder(&mut self, order: &[&str]) {
+        let rest = order.iter().position(|name| *name == "*").unwrap_or(order.len());
+        self.segments.sort_by_key(|segment| {
+            return order.iter().position(|name| *name == segment.name()).unwrap_or(rest);
+        });
+    }
+
+    /// Render every segment not listed in `segments.disabled`. When
+    /// `options.deferred` is set, slow segments that showed something last
+    /// time in this directory are drawn as `async.placeholder` instead.
+    pub fn render(&self, ctx: &Context) -> Vec<StyledText> {
+        return self.render_timed(ctx).into_iter()
+            .filter_map(|(_, rendered)| rendered)
+            .collect();
+    }
+
+    /// Like `render`, also timing each segment. Segments are rendered
+    /// concurrently, each on its own thread since most of their time is
+    /// spent waiting on files and subprocesses, unless `segments.parallel`
+    /// is false. One that's still running after `segments.timeout` (1s by
+    /// default, or `segments.timeouts.<name>`) is abandoned and drawn as
+    /// `segments.timeout_placeholder`, or left out if that's unset.
+    ///
+    /// With the `tokio` feature they run on a runtime instead, where
+    /// `segments.deadline` also bounds the prompt as a whole and the
+    /// commands of an abandoned segment are killed.
+    pub fn render_timed(&self, ctx: &Context) -> Vec<(Timing, Option<StyledText>)> {
+        let disabled = config::strings("segments.disabled");
+        let placeholder = config::string("async.placeholder").unwrap_or("\x1b[90m\u{2026}\x1b[m");
+        let parallel = config::bool("segments.parallel", true);
+        #[cfg(feature = "tokio")]
+        if parallel {
+            if let Some(results) = runtime::render_timed(&self.segments, ctx, &disabled, placeholder) {
+                return results;
+            }
+        }
+        let started = Instant::now();
+        let mut pending = vec![];
+        let mut results = vec![];
+        for segment in &self.segments {
+            let (sender, receiver) = mpsc::sync_channel(1);
+            if disabled.contains(&segment.name()) {
+                log::debug(|| format!("segment {}: hidden, {}", segment.name(), Reason::Disabled));
+                // still reported, for --explain
+                let _ = sender.send((Duration::ZERO, Reason::Disabled, None));
+                pending.push(Pending{name: segment.name().to_owned(), receiver, started, deadline: started});
+                continue;
+            }
+            let (worker, shared) = (Arc::clone(segment), ctx.clone());
+            let spawned = Instant::now();
+            let running = Running::start();
+            thread::spawn(move || {
+                let _running = running;
+                let (reason, rendered) = render_one(worker.as_ref(), &shared, placeholder);
+                let _ = sender.send((spawned.elapsed(), reason, rendered));
+            });
+            let since = if parallel { started } else { spawned };
+            let deadline = since + timeout(segment.name());
+            pending.push(Pending{name: segment.name().to_owned(), receiver, started: since, deadline});
+            if !parallel {
+                results.extend(pending.drain(..).map(|pending| collect(pending, &ctx.path)));
+            }
+        }
+        results.extend(pending.into_iter().map(|pending| collect(pending, &ctx.path)));
+        return results;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    struct Fixed(Option<&'static str>);
+
+    impl Segment for Fixed {
+        fn name(&self) -> &str {
+            return "fixed";
+        }
+
+        fn render(&self, _ctx: &Context) -> Option<StyledText> {
+            let text = self.0.expect("segment failed");
+            return Some(StyledText::from_ansi(text));
+        }
+    }
+
+    #[test]
+    fn test_render_survives_panicking_segment() {
+        let mut registry = Registry::default();
+        registry.register(Fixed(Some("one")));
+        registry.register(Fixed(None));
+        registry.register(Fixed(Some("two")));
+        let ctx = Context::new("/".into(), Options::default());
+        let actual = registry.render(&ctx);
+        assert_eq!(vec![StyledText::from_ansi("one"), StyledText::from_ansi("two")], actual)
+    }
+
+    #[test]
+    fn test_builtin_names_unique() {
+        let mut names: Vec<&str> = SEGMENTS.iter().map(|segment| segment.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(SEGMENTS.len(), names.len())
+    }
+
+    #[rstest]
+    #[case("*prod*", "acme-prod-eu", true)]
+    #[case("prod", "prod", true)]
+    #[case("prod", "production", false)]
+    #[case("dev-?", "dev-1", true)]
+    #[case("*-prod", "prod-eu", false)]
+    #[case("*", "", true)]
+    fn test_glob_match(#[case] pattern: &str, #[case] text: &str, #[case] expected: bool) {
+        let actual = glob_match(pattern, text);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("red", Some("\x1b[31m"))]
+    #[case("bright-blue", Some("\x1b[94m"))]
+    #[case("202", Some("\x1b[38;5;202m"))]
+    #[case("mauve", None)]
+    fn test_color(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = color(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("1.21", "1.21", Ordering::Equal)]
+    #[case("1.9", "1.10", Ordering::Less)]
+    #[case("1.22.1", "1.22", Ordering::Greater)]
+    #[case("0.11.0-dev", "0.11.0", Ordering::Equal)]
+    fn test_version_cmp(#[case] a: &str, #[case] b: &str, #[case] expected: Ordering) {
+        let actual = version_cmp(a, b);
+        assert_eq!(expected, actual)
+    }
+}
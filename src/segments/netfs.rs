@@ -0,0 +1,88 @@
+use std::fs;
+use std::path::Path;
+
+use super::Context;
+
+const ICON: &str = "\x1b[36m\u{F0E8}";
+
+/// Filesystem types served over the network.
+const NETWORK: &[&str] = &["nfs", "nfs4", "cifs", "smb3", "smbfs", "afs", "ceph", "glusterfs", "9p", "davfs", "sshfs"];
+
+/// Undo the octal escapes `/proc/mounts` uses for spaces and the like.
+fn unescape(field: &str) -> String {
+    let mut result = vec![];
+    let bytes = field.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes.get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .and_then(|digits| u8::from_str_radix(std::str::from_utf8(digits).ok()?, 8).ok());
+        match octal {
+            Some(byte) => {
+                result.push(byte);
+                i += 4;
+            },
+            None => {
+                result.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+    return String::from_utf8_lossy(&result).into_owned();
+}
+
+/// The type of the filesystem `path` is on, from the deepest mount point
+/// in a `/proc/mounts` listing above it.
+fn mount_type(mounts: &str, path: &Path) -> Option<String> {
+    return mounts.lines()
+        .filter_map(|line| line.split(' ').nth(1).zip(line.split(' ').nth(2)))
+        .map(|(point, kind)| (unescape(point), kind))
+        .filter(|(point, _)| path.starts_with(point))
+        .max_by_key(|(point, _)| Path::new(point).components().count())
+        .map(|(_, kind)| kind.to_owned());
+}
+
+/// The name shown for a network filesystem type, FUSE ones like
+/// `fuse.sshfs` by what they're mounted with.
+fn network_name(kind: &str) -> Option<&str> {
+    let name = kind.strip_prefix("fuse.").unwrap_or(kind);
+    return NETWORK.contains(&name).then_some(name);
+}
+
+/// Show a network glyph and the filesystem type when the cwd is on NFS,
+/// SMB, SSHFS or the like, where everything run in it is slower.
+pub fn indicator(ctx: &Context) -> Option<String> {
+    let mounts = fs::read_to_string("/proc/mounts").ok()?;
+    let kind = mount_type(&mounts, &ctx.path)?;
+    return Some(format!("{ICON} {}\x1b[m", network_name(&kind)?));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const MOUNTS: &str = "/dev/sda1 / ext4 rw 0 0\n\
+        server:/export /mnt/nfs nfs4 rw 0 0\n\
+        user@host:/ /home/user/my\\040share fuse.sshfs rw 0 0\n";
+
+    #[rstest]
+    #[case("/home/user", Some("ext4"))]
+    #[case("/mnt/nfs/project", Some("nfs4"))]
+    #[case("/mnt/nfsother", Some("ext4"))]
+    #[case("/home/user/my share/src", Some("fuse.sshfs"))]
+    fn test_mount_type(#[case] path: &str, #[case] expected: Option<&str>) {
+        let actual = mount_type(MOUNTS, Path::new(path));
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("nfs4", Some("nfs4"))]
+    #[case("fuse.sshfs", Some("sshfs"))]
+    #[case("fuse.portal", None)]
+    #[case("ext4", None)]
+    fn test_network_name(#[case] kind: &str, #[case] expected: Option<&str>) {
+        let actual = network_name(kind);
+        assert_eq!(expected, actual)
+    }
+}
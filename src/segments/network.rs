@@ -0,0 +1,81 @@
+use std::fs;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::status::{cache, config};
+use super::Context;
+
+const ICON: &str = "\x1b[31m\u{F0B5A}\x1b[m";
+const KEY: &str = "network";
+
+/// Whether `/proc/net/route` lists a default (all-zero destination) route.
+fn ipv4_default_route(route: &str) -> bool {
+    return route.lines().skip(1).any(|line| line.split_whitespace().nth(1) == Some("00000000"));
+}
+
+/// Whether `/proc/net/ipv6_route` lists a `::/0` route through anything but loopback.
+fn ipv6_default_route(route: &str) -> bool {
+    return route.lines().any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        return fields.len() == 10 && fields[0].bytes().all(|byte| byte == b'0')
+            && fields[1] == "00" && fields[9] != "lo";
+    });
+}
+
+/// False only when the routing table is readable and has no way out.
+fn has_default_route() -> bool {
+    let Ok(ipv4) = fs::read_to_string("/proc/net/route") else {
+        return true;
+    };
+    let ipv6 = fs::read_to_string("/proc/net/ipv6_route").unwrap_or_default();
+    return ipv4_default_route(&ipv4) || ipv6_default_route(&ipv6);
+}
+
+/// Try to reach `network.check_host` and cache the outcome; run in the background.
+pub fn refresh() {
+    let host = config::string("network.check_host").unwrap_or("1.1.1.1:443");
+    let online = host.to_socket_addrs().ok()
+        .and_then(|mut addrs| addrs.next())
+        .is_some_and(|addr| TcpStream::connect_timeout(&addr, Duration::from_secs(2)).is_ok());
+    cache::store_refreshed(KEY, if online { "online" } else { "offline" });
+}
+
+/// Show an offline glyph when `network.enabled` is set and there is no
+/// default route, or the last connectivity check failed. Checks older than
+/// `network.ttl` seconds are redone in the background.
+pub fn status(_ctx: &Context) -> Option<String> {
+    if !config::bool("network.enabled", false) {
+        return None;
+    }
+    if !has_default_route() {
+        return Some(ICON.to_owned());
+    }
+    let ttl = u64::try_from(config::integer("network.ttl", 300)).unwrap_or(300);
+    if cache::refreshed(KEY, ttl, "network").as_deref() == Some("offline") {
+        return Some(ICON.to_owned());
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("Iface\tDestination\tGateway\tFlags\nwlan0\t00000000\t0102A8C0\t0003\nwlan0\t0002A8C0\t00000000\t0001\n", true)]
+    #[case("Iface\tDestination\tGateway\tFlags\nwlan0\t0002A8C0\t00000000\t0001\n", false)]
+    fn test_ipv4_default_route(#[case] input: &str, #[case] expected: bool) {
+        let actual = ipv4_default_route(input);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("00000000000000000000000000000000 00 00000000000000000000000000000000 00 fe800000000000000000000000000001 00000400 00000001 00000000 00000003 wlan0\n", true)]
+    #[case("00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 ffffffff 00000001 00000000 00200200 lo\n", false)]
+    #[case("fe800000000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001 wlan0\n", false)]
+    fn test_ipv6_default_route(#[case] input: &str, #[case] expected: bool) {
+        let actual = ipv6_default_route(input);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,46 @@
+use std::env;
+
+use super::Context;
+
+const ICON: &str = "\x1b[38;5;111m\u{F313}";
+
+/// Whether `nix shell` has put store paths on `$PATH`; unlike `nix-shell` and
+/// `nix develop` it doesn't set `$IN_NIX_SHELL`.
+fn store_on_path(path: &str) -> bool {
+    return env::split_paths(path).any(|dir| dir.starts_with("/nix/store"));
+}
+
+/// Show a snowflake with the shell name inside `nix-shell`/`nix develop`
+/// (marking impure shells), or just the snowflake inside `nix shell`.
+pub fn shell(_ctx: &Context) -> Option<String> {
+    if let Ok(kind) = env::var("IN_NIX_SHELL") {
+        let mut result = ICON.to_owned();
+        if let Ok(name) = env::var("name") {
+            result += " ";
+            result += &name;
+        }
+        if kind == "impure" {
+            result += "\x1b[90m*";
+        }
+        return Some(result + "\x1b[m");
+    }
+    if store_on_path(&env::var("PATH").unwrap_or_default()) {
+        return Some(format!("{ICON}\x1b[m"));
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("/nix/store/abc-ripgrep-14.1.0/bin:/usr/bin", true)]
+    #[case("/home/kevna/.nix-profile/bin:/usr/bin", false)]
+    #[case("", false)]
+    fn test_store_on_path(#[case] input: &str, #[case] expected: bool) {
+        let actual = store_on_path(input);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,42 @@
+use std::fs;
+use serde_json::Value;
+
+use crate::status::cache;
+use super::{Context, project};
+
+const ICON: &str = "\x1b[32m\u{E718}";
+
+fn parse_package(contents: &str) -> Option<String> {
+    let package: Value = serde_json::from_str(contents).ok()?;
+    let name = package.get("name")?.as_str()?;
+    return match package.get("version").and_then(Value::as_str) {
+        Some(version) => Some(format!("{name}@{version}")),
+        None => Some(name.to_owned()),
+    };
+}
+
+/// Show `name@version` for the closest package.json, cached on its mtime like the Cargo segment.
+pub fn package(ctx: &Context) -> Option<String> {
+    let manifest = ctx.find_upwards(&project::files("node"))?;
+    let key = format!("node:{}", manifest.display());
+    let package = cache::get_or_insert(&key, &cache::mtime(&manifest)?, || {
+        return parse_package(&fs::read_to_string(&manifest).ok()?);
+    })?;
+    return Some(format!("{ICON}{package}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(r#"{"name": "@kevna/ui", "version": "2.0.0-beta.1"}"#, Some("@kevna/ui@2.0.0-beta.1"))]
+    #[case(r#"{"name": "scratch", "private": true}"#, Some("scratch"))]
+    #[case(r#"{"private": true, "workspaces": ["packages/*"]}"#, None)]
+    #[case("not json", None)]
+    fn test_parse_package(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_package(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+}
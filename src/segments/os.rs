@@ -0,0 +1,85 @@
+use std::env;
+use std::fs;
+use toml::Value;
+
+use crate::status::config;
+use super::{Context, color};
+
+const ICONS: &[(&str, &str)] = &[
+    ("alpine", "\u{F300}"),
+    ("arch", "\u{F303}"),
+    ("centos", "\u{F304}"),
+    ("debian", "\u{F306}"),
+    ("elementary", "\u{F309}"),
+    ("fedora", "\u{F30A}"),
+    ("freebsd", "\u{F30C}"),
+    ("gentoo", "\u{F30D}"),
+    ("linuxmint", "\u{F30E}"),
+    ("macos", "\u{F179}"),
+    ("manjaro", "\u{F312}"),
+    ("nixos", "\u{F313}"),
+    ("opensuse", "\u{F314}"),
+    ("raspbian", "\u{F315}"),
+    ("rhel", "\u{F316}"),
+    ("ubuntu", "\u{F31B}"),
+    ("windows", "\u{F17A}"),
+    ("linux", "\u{F31A}"),
+];
+
+/// The `ID` and `ID_LIKE` entries of `/etc/os-release`, most specific first.
+fn parse_os_release(contents: &str) -> Vec<String> {
+    let mut ids = vec![];
+    for line in contents.lines() {
+        if let Some(id) = line.strip_prefix("ID=") {
+            ids.insert(0, id.trim_matches('"').to_owned());
+        } else if let Some(like) = line.strip_prefix("ID_LIKE=") {
+            ids.extend(like.trim_matches('"').split_whitespace().map(str::to_owned));
+        }
+    }
+    // opensuse reports versioned ids such as opensuse-tumbleweed
+    return ids.into_iter()
+        .map(|id| if id.starts_with("opensuse") { "opensuse".to_owned() } else { id })
+        .collect();
+}
+
+fn icon(id: &str) -> Option<String> {
+    if let Some(icon) = config::value("os.icons").and_then(|icons| icons.get(id)).and_then(Value::as_str) {
+        return Some(icon.to_owned());
+    }
+    return ICONS.iter().find(|(name, _)| *name == id).map(|(_, icon)| (*icon).to_owned());
+}
+
+/// Show the logo of the running OS or distro when `os.enabled` is set;
+/// `os.color` and the `[os.icons]` table override the defaults.
+pub fn logo(_ctx: &Context) -> Option<String> {
+    if !config::bool("os.enabled", false) {
+        return None;
+    }
+    let mut ids = match env::consts::OS {
+        "linux" => fs::read_to_string("/etc/os-release")
+            .or_else(|_| fs::read_to_string("/usr/lib/os-release"))
+            .map(|contents| parse_os_release(&contents))
+            .unwrap_or_default(),
+        _ => vec![],
+    };
+    ids.push(env::consts::OS.to_owned());
+    let icon = ids.iter().find_map(|id| icon(id))?;
+    let color = config::string("os.color").and_then(color).unwrap_or_default();
+    return Some(format!("{color}{icon}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("NAME=\"Ubuntu\"\nID=ubuntu\nID_LIKE=debian\n", &["ubuntu", "debian"])]
+    #[case("ID=\"rocky\"\nID_LIKE=\"rhel centos fedora\"\n", &["rocky", "rhel", "centos", "fedora"])]
+    #[case("ID=\"opensuse-tumbleweed\"\nID_LIKE=\"opensuse suse\"\n", &["opensuse", "opensuse", "suse"])]
+    #[case("", &[])]
+    fn test_parse_os_release(#[case] input: &str, #[case] expected: &[&str]) {
+        let actual = parse_os_release(input);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,170 @@
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+use serde_json::json;
+
+use crate::status::{cache, config};
+use super::{Context, Registry, Segment, StyledText, cached_for, path_fingerprint, run_with_input};
+
+const PREFIX: &str = "statusline-";
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The plugin name of an executable called `statusline-<name>`.
+fn plugin_name(file_name: &str) -> Option<&str> {
+    let name = file_name.strip_prefix(PREFIX)?;
+    if name.is_empty() || name.starts_with('-') {
+        return None;
+    }
+    return Some(name);
+}
+
+/// The name a shell would run `entry` by, if it's executable.
+#[cfg(unix)]
+fn executable_name(entry: &fs::DirEntry) -> Option<String> {
+    let meta = entry.metadata().ok()?;
+    if !meta.is_file() || meta.permissions().mode() & 0o111 == 0 {
+        return None;
+    }
+    return entry.file_name().into_string().ok();
+}
+
+/// Windows goes by the extension rather than a mode bit, and it's left off
+/// when running the file.
+#[cfg(not(unix))]
+fn executable_name(entry: &fs::DirEntry) -> Option<String> {
+    let path = entry.path();
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    if !entry.metadata().ok()?.is_file() || !["exe", "bat", "cmd"].contains(&extension.as_str()) {
+        return None;
+    }
+    return Some(path.file_stem()?.to_str()?.to_owned());
+}
+
+/// Every `statusline-<name>` executable on PATH, the first of each name
+/// winning like a shell lookup would.
+fn scan(path: &OsStr) -> Vec<(String, PathBuf)> {
+    let mut plugins: Vec<(String, PathBuf)> = vec![];
+    for dir in env::split_paths(path) {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut found: Vec<(String, PathBuf)> = entries.flatten()
+            .filter_map(|entry| {
+                let name = plugin_name(&executable_name(&entry)?)?.to_owned();
+                return Some((name, entry.path()));
+            })
+            .filter(|(name, _)| !plugins.iter().any(|(seen, _)| seen == name))
+            .collect();
+        found.sort();
+        plugins.append(&mut found);
+    }
+    return plugins;
+}
+
+/// Scanning PATH is cached until PATH or one of its directories changes.
+fn discover_all() -> Vec<(String, PathBuf)> {
+    let Some(path) = env::var_os("PATH") else {
+        return vec![];
+    };
+    let listing = cache::get_or_insert("plugins:discover", &path_fingerprint(&path), || {
+        let lines: Vec<String> = scan(&path).into_iter()
+            .map(|(name, exe)| format!("{name}\t{}", exe.display()))
+            .collect();
+        return Some(lines.join("\n"));
+    }).unwrap_or_default();
+    return listing.lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(name, exe)| (name.to_owned(), exe.into()))
+        .collect();
+}
+
+/// The context handed to plugins, as a JSON object.
+pub fn context_json(name: &str, ctx: &Context) -> String {
+    return json!({
+        "name": name,
+        "path": ctx.path.to_string_lossy(),
+        "keymap": ctx.options.keymap,
+        "history": ctx.options.history,
+        "status": ctx.options.status,
+    }).to_string();
+}
+
+/// A segment rendered by an external `statusline-<name>` executable, which
+/// gets the context as JSON on stdin and prints the styled segment.
+pub struct Plugin {
+    pub name: String,
+    pub exe: PathBuf,
+}
+
+impl Segment for Plugin {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    /// Runs with `plugins.timeout` (500ms by default), reusing the output
+    /// for `plugins.interval` when that's set.
+    fn render(&self, ctx: &Context) -> Option<StyledText> {
+        let input = context_json(&self.name, ctx);
+        let timeout = config::value("plugins.timeout").and_then(config::duration).unwrap_or(DEFAULT_TIMEOUT);
+        let interval = config::value("plugins.interval").and_then(config::duration);
+        let key = format!("plugin:{}:{}", self.name, cache::digest(&input));
+        let output = cached_for(&key, interval, || {
+            let mut command = Command::new(&self.exe);
+            command.current_dir(&ctx.path);
+            return run_with_input(command, Some(input.clone().into_bytes()), timeout);
+        })?;
+        let output = output.lines().next().unwrap_or_default().trim_end();
+        if output.is_empty() {
+            return None;
+        }
+        return Some(StyledText::from_ansi(output));
+    }
+
+    fn slow(&self) -> bool {
+        return true;
+    }
+}
+
+/// The plugins found on PATH when `plugins.enabled` is set, limited to
+/// the names in `plugins.allow` if that's given, followed by any shared
+/// libraries in `[plugins.libraries]` with the `plugins` feature and the
+/// modules in `[plugins.wasm]` with the `wasm` feature.
+pub fn discover() -> Registry {
+    let mut registry = Registry::default();
+    if !config::bool("plugins.enabled", false) {
+        return registry;
+    }
+    let allow = config::strings("plugins.allow");
+    for (name, exe) in discover_all() {
+        if allow.is_empty() || allow.contains(&name.as_str()) {
+            registry.register(Plugin{name, exe});
+        }
+    }
+    #[cfg(feature = "plugins")]
+    registry.extend(super::dylib::load());
+    #[cfg(feature = "wasm")]
+    registry.extend(super::wasm::load());
+    return registry;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("statusline-k9s", Some("k9s"))]
+    #[case("statusline-ci-status", Some("ci-status"))]
+    #[case("statusline-", None)]
+    #[case("statusline", None)]
+    #[case("other-tool", None)]
+    fn test_plugin_name(#[case] file_name: &str, #[case] expected: Option<&str>) {
+        let actual = plugin_name(file_name);
+        assert_eq!(expected, actual)
+    }
+}
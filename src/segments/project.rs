@@ -0,0 +1,59 @@
+use crate::status::config;
+use super::Context;
+
+pub struct Marker {
+    pub file: &'static str,
+    pub kind: &'static str,
+    pub icon: &'static str,
+}
+
+/// Files identifying a project type. The language segments consult this
+/// table too, so every segment probes the same (cached) set of files.
+pub const MARKERS: &[Marker] = &[
+    Marker{file: "Cargo.toml", kind: "rust", icon: "\x1b[38;5;166m\u{E7A8}"},
+    Marker{file: "package.json", kind: "node", icon: "\x1b[32m\u{E718}"},
+    Marker{file: "pyproject.toml", kind: "python", icon: "\x1b[33m\u{E73C}"},
+    Marker{file: "setup.py", kind: "python", icon: "\x1b[33m\u{E73C}"},
+    Marker{file: "requirements.txt", kind: "python", icon: "\x1b[33m\u{E73C}"},
+    Marker{file: "go.mod", kind: "go", icon: "\x1b[36m\u{E627}"},
+    Marker{file: "Gemfile", kind: "ruby", icon: "\x1b[31m\u{E791}"},
+    Marker{file: "pom.xml", kind: "java", icon: "\x1b[38;5;166m\u{E738}"},
+    Marker{file: "build.gradle", kind: "java", icon: "\x1b[38;5;166m\u{E738}"},
+    Marker{file: "build.gradle.kts", kind: "java", icon: "\x1b[38;5;166m\u{E738}"},
+    Marker{file: "mix.exs", kind: "elixir", icon: "\x1b[35m\u{E62D}"},
+    Marker{file: "build.zig", kind: "zig", icon: "\x1b[38;5;214m\u{E6A9}"},
+    Marker{file: "stack.yaml", kind: "haskell", icon: "\x1b[35m\u{E777}"},
+    Marker{file: "cabal.project", kind: "haskell", icon: "\x1b[35m\u{E777}"},
+    Marker{file: "Dockerfile", kind: "docker", icon: "\x1b[34m\u{F308}"},
+    Marker{file: "compose.yaml", kind: "docker", icon: "\x1b[34m\u{F308}"},
+    Marker{file: "docker-compose.yml", kind: "docker", icon: "\x1b[34m\u{F308}"},
+];
+
+/// The marker files for a project kind.
+pub fn files(kind: &str) -> Vec<&'static str> {
+    return MARKERS.iter()
+        .filter(|marker| marker.kind == kind)
+        .map(|marker| marker.file)
+        .collect();
+}
+
+/// Show an icon per project type found in the closest directory holding
+/// any marker file, when `project.enabled` is set.
+pub fn icons(ctx: &Context) -> Option<String> {
+    if !config::bool("project.enabled", false) {
+        return None;
+    }
+    for dir in ctx.path.ancestors() {
+        let mut kinds: Vec<&Marker> = vec![];
+        for marker in MARKERS {
+            if !kinds.iter().any(|found| found.kind == marker.kind) && ctx.is_file(&dir.join(marker.file)) {
+                kinds.push(marker);
+            }
+        }
+        if !kinds.is_empty() {
+            let icons: Vec<&str> = kinds.iter().map(|marker| marker.icon).collect();
+            return Some(icons.join("") + "\x1b[m");
+        }
+    }
+    return None;
+}
@@ -0,0 +1,241 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+use toml::{Table, Value};
+
+use super::{Context, project};
+
+const ICON: &str = "\x1b[33m\u{E73C}";
+
+fn pyenv_root() -> Option<PathBuf> {
+    if let Some(root) = env::var_os("PYENV_ROOT") {
+        return Some(PathBuf::from(root));
+    }
+    return env::var_os("HOME").map(|home| Path::new(&home).join(".pyenv"));
+}
+
+/// The versions in a pyenv version file, joined the way pyenv prints them.
+fn parse_version_file(contents: &str) -> Option<String> {
+    let versions: Vec<&str> = contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    if versions.is_empty() {
+        return None;
+    }
+    return Some(versions.join(":"));
+}
+
+fn global_version() -> String {
+    let contents = pyenv_root().and_then(|root| fs::read_to_string(root.join("version")).ok());
+    return contents.as_deref().and_then(parse_version_file).unwrap_or("system".to_owned());
+}
+
+/// The pyenv selection from `$PYENV_VERSION` or the closest `.python-version`.
+fn local_version(path: &Path) -> Option<String> {
+    if let Ok(version) = env::var("PYENV_VERSION") {
+        return Some(version);
+    }
+    for dir in path.ancestors() {
+        if let Ok(contents) = fs::read_to_string(dir.join(".python-version")) {
+            return parse_version_file(&contents);
+        }
+    }
+    return None;
+}
+
+/// Show the pyenv python when it differs from the global default. An active
+/// virtualenv decides the interpreter instead, so the environment segment
+/// reports it and this one stays hidden.
+pub fn pyenv(ctx: &Context) -> Option<String> {
+    if env::var_os("VIRTUAL_ENV").is_some() {
+        return None;
+    }
+    let version = local_version(&ctx.path)?;
+    if version == global_version() {
+        return None;
+    }
+    return Some(format!("{ICON}{version}\x1b[m"));
+}
+
+struct Venv {
+    name: String,
+    version: Option<String>,
+}
+
+/// Read the interpreter version and prompt name from a `pyvenv.cfg`.
+fn parse_pyvenv_cfg(contents: &str) -> (Option<String>, Option<String>) {
+    let mut version = None;
+    let mut prompt = None;
+    for line in contents.lines() {
+        if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim().trim_matches(['"', '\'']).to_owned();
+            match key.trim() {
+                // uv writes version_info, virtualenv and poetry write version
+                "version_info" | "version" => version = Some(value),
+                "prompt" => prompt = Some(value),
+                _ => {},
+            }
+        }
+    }
+    return (version, prompt);
+}
+
+fn read_venv(dir: &Path) -> Option<Venv> {
+    let contents = fs::read_to_string(dir.join("pyvenv.cfg")).ok()?;
+    let (version, prompt) = parse_pyvenv_cfg(&contents);
+    let name = prompt.or_else(|| Some(dir.file_name()?.to_string_lossy().into_owned()))?;
+    return Some(Venv{
+        name,
+        version,
+    });
+}
+
+/// The project name Poetry derives its environment names from, normalized
+/// as a package name is: lowercase, with each run of `-`, `_` and `.` as a
+/// single `-`.
+fn project_name(pyproject: &str) -> Option<String> {
+    let table = pyproject.parse::<Table>().ok()?;
+    let name = table.get("tool").and_then(|tool| tool.get("poetry")).and_then(|poetry| poetry.get("name"))
+        .or_else(|| table.get("project")?.get("name"))
+        .and_then(Value::as_str)?;
+    let mut normalized = String::new();
+    for c in name.to_lowercase().chars() {
+        match c {
+            '-' | '_' | '.' if normalized.ends_with('-') => {},
+            '-' | '_' | '.' => normalized.push('-'),
+            c => normalized.push(c),
+        }
+    }
+    return Some(normalized);
+}
+
+const BASE64_URL: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// What Poetry names the environments of the project `name` in `root`,
+/// before their `-py<version>`: the name, cut to 42 characters and with
+/// those special to a shell as `_`, then the first 8 characters of the
+/// URL-safe base64 SHA-256 of the directory, so that projects sharing a
+/// name don't share an environment.
+fn poetry_env_name(name: &str, root: &str) -> String {
+    let name: String = name.chars()
+        .map(|c| if " $`!*@\"\\\r\n\t".contains(c) { '_' } else { c })
+        .take(42)
+        .collect();
+    let hash = Sha256::digest(root.as_bytes());
+    // 6 bytes are exactly 8 base64 characters
+    let bits = hash[..6].iter().fold(0u64, |bits, byte| bits << 8 | u64::from(*byte));
+    let encoded: String = (0..8).rev().map(|i| char::from(BASE64_URL[(bits >> (i * 6) & 63) as usize])).collect();
+    return format!("{name}-{encoded}");
+}
+
+/// The directory as Python's `os.path.normcase(os.path.realpath(..))`
+/// spells it, which is what Poetry hashes.
+fn poetry_root(dir: &Path) -> Option<String> {
+    let real = fs::canonicalize(dir).ok()?.to_string_lossy().into_owned();
+    if cfg!(windows) {
+        return Some(real.trim_start_matches(r"\\?\").replace('/', "\\").to_lowercase());
+    }
+    return Some(real);
+}
+
+/// The Python version Poetry last used for the environment `env_name`, from
+/// its `envs.toml`, like `3.12`.
+fn poetry_minor(envs: &str, env_name: &str) -> Option<String> {
+    let table = envs.parse::<Table>().ok()?;
+    return table.get(env_name)?.get("minor")?.as_str().map(str::to_owned);
+}
+
+/// Poetry keeps environments out of the project, in `<cache>/virtualenvs/<name>-<hash>-py<version>`.
+fn poetry_venv(pyproject: &Path) -> Option<Venv> {
+    let name = project_name(&fs::read_to_string(pyproject).ok()?)?;
+    let cache = match env::var_os("POETRY_CACHE_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => match env::var_os("XDG_CACHE_HOME") {
+            Some(dir) => Path::new(&dir).join("pypoetry"),
+            None => Path::new(&env::var_os("HOME")?).join(".cache/pypoetry"),
+        },
+    };
+    let virtualenvs = cache.join("virtualenvs");
+    let env_name = poetry_env_name(&name, &poetry_root(pyproject.parent()?)?);
+    let envs = fs::read_to_string(virtualenvs.join("envs.toml")).unwrap_or_default();
+    if let Some(minor) = poetry_minor(&envs, &env_name) {
+        return read_venv(&virtualenvs.join(format!("{env_name}-py{minor}")));
+    }
+    let prefix = format!("{env_name}-py");
+    let mut dirs: Vec<PathBuf> = fs::read_dir(&virtualenvs).ok()?.flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+        .collect();
+    dirs.sort();
+    return read_venv(dirs.last()?);
+}
+
+/// Show the virtualenv that Poetry or uv manage for the project, whether or
+/// not it's activated: `$VIRTUAL_ENV` first, then an in-project `.venv`,
+/// then Poetry's cache.
+pub fn environment(ctx: &Context) -> Option<String> {
+    let venv = if let Some(dir) = env::var_os("VIRTUAL_ENV") {
+        read_venv(Path::new(&dir))
+    } else {
+        let root = ctx.find_upwards(&project::files("python"))?.parent()?.to_path_buf();
+        read_venv(&root.join(".venv")).or_else(|| poetry_venv(&root.join("pyproject.toml")))
+    }?;
+    return match venv.version {
+        Some(version) => Some(format!("{ICON}{} {version}\x1b[m", venv.name)),
+        None => Some(format!("{ICON}{}\x1b[m", venv.name)),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("3.12.1\n", Some("3.12.1"))]
+    #[case("3.12.1\n3.11.7\n", Some("3.12.1:3.11.7"))]
+    #[case("# pinned\n\n", None)]
+    fn test_parse_version_file(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_version_file(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("home = /usr/bin\nimplementation = CPython\nuv = 0.4.18\nversion_info = 3.12.6\nprompt = app\n", Some("3.12.6"), Some("app"))]
+    #[case("home = /usr/bin\ninclude-system-site-packages = false\nversion = 3.11.7\n", Some("3.11.7"), None)]
+    #[case("", None, None)]
+    fn test_parse_pyvenv_cfg(#[case] input: &str, #[case] version: Option<&str>, #[case] prompt: Option<&str>) {
+        let actual = parse_pyvenv_cfg(input);
+        assert_eq!((version.map(str::to_owned), prompt.map(str::to_owned)), actual)
+    }
+
+    #[rstest]
+    #[case("[tool.poetry]\nname = \"My_App\"\n", Some("my-app"))]
+    #[case("[project]\nname = \"service.api\"\n", Some("service-api"))]
+    #[case("[project]\nname = \"Service__API-\"\n", Some("service-api-"))]
+    #[case("[build-system]\nrequires = []\n", None)]
+    fn test_project_name(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = project_name(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("app", "/home/kevna/app", "app-wuCnabxO")]
+    #[case("my app", "/srv/my project", "my_app-DauAKNz7")]
+    #[case("a-very-long-project-name-that-goes-on-and-on", "/home/kevna/app", "a-very-long-project-name-that-goes-on-and--wuCnabxO")]
+    fn test_poetry_env_name(#[case] name: &str, #[case] root: &str, #[case] expected: &str) {
+        let actual = poetry_env_name(name, root);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("[app-wuCnabxO]\nminor = \"3.12\"\npatch = \"3.12.4\"\n", "app-wuCnabxO", Some("3.12"))]
+    #[case("[app-tools-Zx81Kq0a]\nminor = \"3.11\"\n", "app-wuCnabxO", None)]
+    #[case("", "app-wuCnabxO", None)]
+    fn test_poetry_minor(#[case] envs: &str, #[case] env_name: &str, #[case] expected: Option<&str>) {
+        let actual = poetry_minor(envs, env_name);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+}
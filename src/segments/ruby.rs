@@ -0,0 +1,106 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::sync::LazyLock;
+use regex::Regex;
+
+use super::{Context, cached_output, project, tools};
+
+const ICON: &str = "\x1b[31m\u{E791}";
+
+/// Read the version from a `.ruby-version` file, dropping rvm's `ruby-` prefix.
+fn parse_ruby_version(contents: &str) -> Option<String> {
+    let line = contents.lines().map(str::trim).find(|line| !line.is_empty())?;
+    return Some(line.trim_start_matches("ruby-").to_owned());
+}
+
+static RUBY_OUTPUT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^ruby (\d+(\.\d+)*)").unwrap());
+
+/// Parse `ruby 3.2.2p53 (2023-03-30 revision e51014f9c0) [x86_64-linux]` into `3.2.2`.
+fn parse_ruby_output(output: &str) -> Option<String> {
+    return RUBY_OUTPUT.captures(output).map(|caps| caps[1].to_owned());
+}
+
+static GEMFILE_RUBY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?m)^\s*ruby\s+["'](\d+(\.\d+)*)["']"#).unwrap());
+
+/// The exact ruby version pinned in a Gemfile with `ruby "3.2.2"`, if any.
+fn parse_gemfile(contents: &str) -> Option<String> {
+    return GEMFILE_RUBY.captures(contents).map(|caps| caps[1].to_owned());
+}
+
+fn version_file(path: &Path) -> Option<String> {
+    for dir in path.ancestors() {
+        if let Ok(contents) = fs::read_to_string(dir.join(".ruby-version")) {
+            return parse_ruby_version(&contents);
+        }
+        if let Some(version) = tools::tool_version(dir, "ruby") {
+            return Some(version);
+        }
+    }
+    return None;
+}
+
+/// Resolve the active ruby the way rbenv, asdf and rvm would, asking ruby itself last.
+fn active_version(path: &Path) -> Option<String> {
+    for var in ["RBENV_VERSION", "ASDF_RUBY_VERSION"] {
+        if let Ok(version) = env::var(var) {
+            return Some(version);
+        }
+    }
+    if let Some(version) = version_file(path) {
+        return Some(version);
+    }
+    if let Ok(version) = env::var("RUBY_VERSION") {
+        return Some(version.trim_start_matches("ruby-").to_owned());
+    }
+    // with no version file above the path, the ruby found is the same
+    // wherever it's asked from, so it's only asked again when it changes
+    return parse_ruby_output(&cached_output("ruby", &["--version"])?);
+}
+
+/// Show the active ruby inside Ruby projects, in red when the Gemfile pins another version.
+pub fn version(ctx: &Context) -> Option<String> {
+    let gemfile = ctx.find_upwards(&project::files("ruby"));
+    if gemfile.is_none() && ctx.find_upwards(&[".ruby-version"]).is_none() {
+        return None;
+    }
+    let active = active_version(&ctx.path)?;
+    let required = gemfile.and_then(|file| fs::read_to_string(file).ok())
+        .and_then(|contents| parse_gemfile(&contents));
+    if required.is_some_and(|required| required != active) {
+        return Some(format!("{ICON}\x1b[91m{active}\x1b[m"));
+    }
+    return Some(format!("{ICON}{active}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("3.2.2\n", Some("3.2.2"))]
+    #[case("ruby-3.1.4", Some("3.1.4"))]
+    #[case("\n", None)]
+    fn test_parse_ruby_version(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_ruby_version(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("ruby 3.2.2p53 (2023-03-30 revision e51014f9c0) [x86_64-linux]", Some("3.2.2"))]
+    #[case("jruby 9.4.3.0 (3.1.4)", None)]
+    fn test_parse_ruby_output(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_ruby_output(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("source \"https://rubygems.org\"\nruby \"3.2.2\"\n", Some("3.2.2"))]
+    #[case("source 'https://rubygems.org'\nruby '~> 3.2'\n", None)]
+    #[case("gem 'rails'\n", None)]
+    fn test_parse_gemfile(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_gemfile(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+}
@@ -0,0 +1,96 @@
+use std::cell::Cell;
+use std::io;
+use std::path::Path;
+use std::process::{Command, Output};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+use tokio::runtime::{Builder, Handle, Runtime};
+use tokio::task::JoinHandle;
+
+use crate::status::{config, log};
+use super::{finished, panicked, render_one, timed_out, timeout, Context, Reason, Running, Segment, StyledText, Timing};
+
+static RUNTIME: LazyLock<Option<Runtime>> = LazyLock::new(|| Builder::new_multi_thread().enable_all().build().ok());
+
+thread_local! {
+    /// When the segment rendering on this thread has to be finished by.
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+type Rendered = (Duration, Reason, Option<StyledText>);
+
+/// The segment's own `segments.timeout`, cut short by `segments.deadline`
+/// for the prompt as a whole when that's set.
+fn deadline(started: Instant, name: &str) -> Instant {
+    let own = started + timeout(name);
+    return match config::value("segments.deadline").and_then(config::duration) {
+        Some(deadline) => own.min(started + deadline),
+        None => own,
+    };
+}
+
+/// Run a command to completion, or until the deadline of the segment that
+/// runs it, when it's killed rather than left running after the prompt.
+pub fn output(mut command: Command) -> io::Result<Output> {
+    let (Some(deadline), Ok(handle)) = (DEADLINE.get(), Handle::try_current()) else {
+        return command.output();
+    };
+    let mut command = tokio::process::Command::from(command);
+    command.kill_on_drop(true);
+    return handle.block_on(async {
+        return tokio::time::timeout_at(deadline.into(), command.output()).await
+            .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()));
+    });
+}
+
+async fn collect(name: String, started: Instant, deadline: Instant, task: JoinHandle<Rendered>, path: &Path) -> (Timing, Option<StyledText>) {
+    return match tokio::time::timeout_at(deadline.into(), task).await {
+        Ok(Ok((took, reason, rendered))) => finished(name, took, reason, rendered),
+        Ok(Err(_)) => panicked(name),
+        Err(_) => timed_out(name, deadline - started, path),
+    };
+}
+
+/// Render the segments as tasks on a shared tokio runtime, each with a
+/// deadline after which it's given up on and the commands it's running are
+/// killed, so nothing a prompt started outlives it. None when the runtime
+/// can't be started, or when the caller is already on one, which can't be
+/// blocked on.
+pub fn render_timed(segments: &[Arc<dyn Segment>], ctx: &Context, disabled: &[&str], placeholder: &'static str) -> Option<Vec<(Timing, Option<StyledText>)>> {
+    if Handle::try_current().is_ok() {
+        return None;
+    }
+    let runtime = RUNTIME.as_ref()?;
+    let started = Instant::now();
+    let mut tasks = vec![];
+    for segment in segments {
+        let name = segment.name().to_owned();
+        if disabled.contains(&segment.name()) {
+            log::debug(|| format!("segment {name}: hidden, {}", Reason::Disabled));
+            tasks.push((name, started, None));
+            continue;
+        }
+        let deadline = deadline(started, &name);
+        let (worker, ctx) = (Arc::clone(segment), ctx.clone());
+        let running = Running::start();
+        let task = runtime.spawn_blocking(move || {
+            let _running = running;
+            DEADLINE.set(Some(deadline));
+            let spawned = Instant::now();
+            let (reason, rendered) = render_one(worker.as_ref(), &ctx, placeholder);
+            DEADLINE.set(None);
+            return (spawned.elapsed(), reason, rendered);
+        });
+        tasks.push((name, deadline, Some(task)));
+    }
+    return Some(runtime.block_on(async {
+        let mut results = vec![];
+        for (name, deadline, task) in tasks {
+            results.push(match task {
+                Some(task) => collect(name, started, deadline, task, &ctx.path).await,
+                None => finished(name, Duration::ZERO, Reason::Disabled, None),
+            });
+        }
+        return results;
+    }));
+}
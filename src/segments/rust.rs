@@ -0,0 +1,120 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use regex::Regex;
+
+use crate::status::cache;
+use super::{Context, project, run_command, run_command_in};
+
+const ICON: &str = "\x1b[38;5;166m\u{E7A8}";
+
+static TOOLCHAIN_CHANNEL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?m)^\s*channel\s*=\s*["']([^"']+)["']"#).unwrap());
+
+/// Extract the channel from a `rust-toolchain.toml` or legacy `rust-toolchain` file.
+fn parse_toolchain_file(contents: &str) -> Option<String> {
+    if let Some(caps) = TOOLCHAIN_CHANNEL.captures(contents) {
+        return Some(caps[1].to_owned());
+    }
+    // the legacy format is just the bare toolchain name
+    let line = contents.lines().map(str::trim).find(|line| !line.is_empty())?;
+    if line.starts_with('[') || line.contains('=') {
+        return None;
+    }
+    return Some(line.to_owned());
+}
+
+static TARGET_TRIPLE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(.+?)-(x86_64|i686|aarch64|arm\w*|riscv64\w*|powerpc\w*|s390x|loongarch64)-.+$").unwrap());
+
+/// Strip the host triple from a toolchain name, `nightly-x86_64-unknown-linux-gnu` -> `nightly`.
+fn short_name(toolchain: &str) -> String {
+    if let Some(caps) = TARGET_TRIPLE.captures(toolchain) {
+        return caps[1].to_owned();
+    }
+    return toolchain.to_owned();
+}
+
+fn rustup_home() -> Option<PathBuf> {
+    if let Some(home) = env::var_os("RUSTUP_HOME") {
+        return Some(PathBuf::from(home));
+    }
+    return env::var_os("HOME").map(|home| Path::new(&home).join(".rustup"));
+}
+
+static DEFAULT_TOOLCHAIN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?m)^default_toolchain\s*=\s*"([^"]+)""#).unwrap());
+
+fn default_toolchain() -> Option<String> {
+    if let Some(home) = rustup_home() {
+        if let Ok(settings) = fs::read_to_string(home.join("settings.toml")) {
+            if let Some(caps) = DEFAULT_TOOLCHAIN.captures(&settings) {
+                return Some(caps[1].to_owned());
+            }
+        }
+    }
+    let output = run_command("rustup", &["default"])?;
+    return output.split_whitespace().next().map(str::to_owned);
+}
+
+/// Ask rustup for directory overrides set with `rustup override`, which
+/// it keeps in its `settings.toml`, so the answer is cached on that for
+/// `project`, the directory of the project's manifest.
+fn active_override(path: &Path, project: &Path) -> Option<String> {
+    let settings = cache::mtime(&rustup_home()?.join("settings.toml")).unwrap_or_default();
+    let key = format!("rustup-override:{}", project.display());
+    let toolchain = cache::get_or_insert(&key, &settings, || {
+        let output = run_command_in(path, "rustup", &["show", "active-toolchain"])?;
+        if output.contains("(default)") {
+            return Some(String::new());
+        }
+        return output.split_whitespace().next().map(str::to_owned);
+    })?;
+    return Some(toolchain).filter(|toolchain| !toolchain.is_empty());
+}
+
+/// Show the rustup toolchain when a project pins something other than the default.
+pub fn toolchain(ctx: &Context) -> Option<String> {
+    let active = match env::var("RUSTUP_TOOLCHAIN") {
+        Ok(toolchain) => toolchain,
+        Err(_) => match ctx.find_upwards(&["rust-toolchain.toml", "rust-toolchain"]) {
+            Some(file) => parse_toolchain_file(&fs::read_to_string(file).ok()?)?,
+            None => {
+                let manifest = ctx.find_upwards(&project::files("rust"))?;
+                active_override(&ctx.path, manifest.parent()?)?
+            },
+        },
+    };
+    let active = short_name(&active);
+    if let Some(default) = default_toolchain() {
+        if short_name(&default) == active {
+            return None;
+        }
+    }
+    return Some(format!("{ICON}{active}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("[toolchain]\nchannel = \"nightly-2024-01-01\"\n", Some("nightly-2024-01-01"))]
+    #[case("[toolchain]\nchannel = 'stable'\ncomponents = [\"rustfmt\"]\n", Some("stable"))]
+    #[case("nightly\n", Some("nightly"))]
+    #[case("\n1.70.0\n", Some("1.70.0"))]
+    #[case("[toolchain]\ncomponents = [\"rustfmt\"]\n", None)]
+    fn test_parse_toolchain_file(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_toolchain_file(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+
+    #[rstest]
+    #[case("stable-x86_64-unknown-linux-gnu", "stable")]
+    #[case("nightly-2024-01-01-aarch64-apple-darwin", "nightly-2024-01-01")]
+    #[case("1.70.0", "1.70.0")]
+    #[case("beta", "beta")]
+    fn test_short_name(#[case] input: &str, #[case] expected: &str) {
+        let actual = short_name(input);
+        assert_eq!(expected, actual)
+    }
+}
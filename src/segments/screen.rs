@@ -0,0 +1,41 @@
+use std::env;
+
+use crate::status::config;
+use super::Context;
+
+const ICON: &str = "\x1b[32m\u{EBC8} ";
+
+/// `$STY` is `<pid>.<name>`; the name defaults to `<tty>.<host>`.
+fn session_name(sty: &str) -> Option<&str> {
+    let (_pid, name) = sty.split_once('.')?;
+    if name.is_empty() {
+        return None;
+    }
+    return Some(name);
+}
+
+/// Show the GNU screen session name inside screen when `screen.enabled` is set.
+pub fn session(_ctx: &Context) -> Option<String> {
+    if !config::bool("screen.enabled", false) {
+        return None;
+    }
+    let sty = env::var("STY").ok()?;
+    let name = session_name(&sty)?;
+    return Some(format!("{ICON}{name}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("12345.work", Some("work"))]
+    #[case("12345.pts-0.host", Some("pts-0.host"))]
+    #[case("12345.", None)]
+    #[case("garbage", None)]
+    fn test_session_name(#[case] sty: &str, #[case] expected: Option<&str>) {
+        let actual = session_name(sty);
+        assert_eq!(expected, actual)
+    }
+}
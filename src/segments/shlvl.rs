@@ -0,0 +1,16 @@
+use std::env;
+
+use crate::status::config;
+
+use super::Context;
+
+const ICON: &str = "\x1b[33m\u{29C9}";
+
+/// Show the shell nesting depth once `$SHLVL` exceeds `shlvl.threshold` (default 1).
+pub fn depth(_ctx: &Context) -> Option<String> {
+    let level: i64 = env::var("SHLVL").ok()?.trim().parse().ok()?;
+    if level <= config::integer("shlvl.threshold", 1) {
+        return None;
+    }
+    return Some(format!("{ICON}{level}\x1b[m"));
+}
@@ -0,0 +1,72 @@
+use std::env;
+#[cfg(unix)]
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::time::Duration;
+
+use crate::status::config;
+use super::Context;
+
+const ICON: &str = "\u{F0306}";
+#[cfg(unix)]
+const REQUEST_IDENTITIES: u8 = 11;
+#[cfg_attr(not(unix), allow(dead_code))]
+const IDENTITIES_ANSWER: u8 = 12;
+
+/// The key count from the start of an `SSH2_AGENT_IDENTITIES_ANSWER`:
+/// length, message type, then the number of keys, all big-endian.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn parse_identities_answer(header: &[u8; 9]) -> Option<u32> {
+    if header[4] != IDENTITIES_ANSWER {
+        return None;
+    }
+    return Some(u32::from_be_bytes([header[5], header[6], header[7], header[8]]));
+}
+
+/// Ask the agent for its identities directly over the socket instead of
+/// spawning `ssh-add -l`.
+#[cfg(unix)]
+fn count_identities(socket: &str) -> Option<u32> {
+    let mut stream = UnixStream::connect(socket).ok()?;
+    stream.set_read_timeout(Some(Duration::from_millis(200))).ok()?;
+    stream.set_write_timeout(Some(Duration::from_millis(200))).ok()?;
+    stream.write_all(&[0, 0, 0, 1, REQUEST_IDENTITIES]).ok()?;
+    let mut header = [0; 9];
+    stream.read_exact(&mut header).ok()?;
+    return parse_identities_answer(&header);
+}
+
+/// Windows' agent listens on a named pipe rather than a socket.
+#[cfg(not(unix))]
+fn count_identities(_socket: &str) -> Option<u32> {
+    return None;
+}
+
+/// Show how many keys ssh-agent holds when `ssh.enabled` is set, in red when
+/// it's empty and the next push would prompt for a passphrase.
+pub fn agent(_ctx: &Context) -> Option<String> {
+    if !config::bool("ssh.enabled", false) {
+        return None;
+    }
+    let socket = env::var("SSH_AUTH_SOCK").ok()?;
+    let count = count_identities(&socket)?;
+    let color = if count == 0 { "\x1b[31m" } else { "\x1b[90m" };
+    return Some(format!("{color}{ICON}{count}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case([0, 0, 0, 5, 12, 0, 0, 0, 0], Some(0))]
+    #[case([0, 0, 1, 40, 12, 0, 0, 0, 2], Some(2))]
+    #[case([0, 0, 0, 1, 5, 0, 0, 0, 0], None)]
+    fn test_parse_identities_answer(#[case] input: [u8; 9], #[case] expected: Option<u32>) {
+        let actual = parse_identities_answer(&input);
+        assert_eq!(expected, actual)
+    }
+}
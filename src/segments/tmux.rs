@@ -0,0 +1,20 @@
+use std::env;
+
+use crate::status::config;
+use super::{Context, printable, run_command};
+
+const ICON: &str = "\x1b[32m\u{EBC8} ";
+
+/// Show the tmux session name inside tmux when `tmux.enabled` is set.
+pub fn session(_ctx: &Context) -> Option<String> {
+    if !config::bool("tmux.enabled", false) {
+        return None;
+    }
+    env::var_os("TMUX")?;
+    // session names can hold any character, escapes included
+    let name = printable(&run_command("tmux", &["display-message", "-p", "#S"])?);
+    if name.is_empty() {
+        return None;
+    }
+    return Some(format!("{ICON}{name}\x1b[m"));
+}
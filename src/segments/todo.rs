@@ -0,0 +1,50 @@
+use crate::status::{cache, config};
+use super::{Context, run_command_in};
+
+const ICON: &str = "\x1b[33m\u{F0AE} ";
+
+/// Sum the per-file `path:count` lines printed by `git grep -c`.
+fn total(output: &str) -> u64 {
+    return output.lines()
+        .filter_map(|line| line.rsplit_once(':')?.1.parse::<u64>().ok())
+        .sum();
+}
+
+/// Count TODO/FIXME markers in tracked files when `todo.enabled` is set.
+/// Grepping a big tree is slow so the total is cached until HEAD or the
+/// index change.
+pub fn markers(ctx: &Context) -> Option<String> {
+    if !config::bool("todo.enabled", false) {
+        return None;
+    }
+    let state = ctx.repo().output(&["rev-parse", "--show-toplevel", "HEAD", "--git-path", "index"])?;
+    let mut state = state.lines();
+    let (root, head, index) = (state.next()?, state.next()?, state.next()?);
+    let index = ctx.path.join(index);
+    let fingerprint = format!("{head}:{}", cache::mtime(&index).unwrap_or_default());
+    let count = cache::get_or_insert(&format!("todo:{root}"), &fingerprint, || {
+        // `:/` searches from the root whichever directory git runs in
+        let output = run_command_in(&ctx.path, "git", &["grep", "-c", "-w", "-E", "TODO|FIXME", "--", ":/"])
+            .unwrap_or_default();
+        return Some(total(&output).to_string());
+    })?;
+    if count == "0" {
+        return None;
+    }
+    return Some(format!("{ICON}{count}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("", 0)]
+    #[case("src/main.rs:2\n", 2)]
+    #[case("src/main.rs:2\nodd:name.rs:3\nREADME.md:1", 6)]
+    fn test_total(#[case] output: &str, #[case] expected: u64) {
+        let actual = total(output);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+
+use crate::status::config;
+use super::Context;
+
+const ICON: &str = "\x1b[35m\u{F0AD}";
+
+/// Tool/version pairs from an asdf `.tool-versions` file; extra versions are fallbacks.
+fn parse_tool_versions(contents: &str) -> Vec<(String, String)> {
+    let mut result = vec![];
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut words = line.split_whitespace();
+        if let (Some(tool), Some(version)) = (words.next(), words.next()) {
+            result.push((tool.to_owned(), version.to_owned()));
+        }
+    }
+    return result;
+}
+
+/// Tool/version pairs from the `[tools]` table of a `mise.toml`, where a
+/// version may be a string, a list of fallbacks, or a `{ version = ... }` table.
+fn parse_mise_toml(contents: &str) -> Vec<(String, String)> {
+    let Ok(table) = contents.parse::<Table>() else {
+        return vec![];
+    };
+    let Some(tools) = table.get("tools").and_then(Value::as_table) else {
+        return vec![];
+    };
+    let mut result = vec![];
+    for (tool, value) in tools {
+        let version = match value {
+            Value::Array(versions) => versions.first(),
+            Value::Table(options) => options.get("version"),
+            value => Some(value),
+        };
+        if let Some(version) = version.and_then(Value::as_str) {
+            result.push((tool.to_owned(), version.to_owned()));
+        }
+    }
+    return result;
+}
+
+/// The tools pinned in one directory, with mise's files taking precedence.
+fn pinned_in(dir: &Path) -> Vec<(String, String)> {
+    for name in ["mise.toml", ".mise.toml"] {
+        if let Ok(contents) = fs::read_to_string(dir.join(name)) {
+            return parse_mise_toml(&contents);
+        }
+    }
+    if let Ok(contents) = fs::read_to_string(dir.join(".tool-versions")) {
+        return parse_tool_versions(&contents);
+    }
+    return vec![];
+}
+
+/// The version pinned for `tool` in `dir` by asdf or mise, if any.
+pub fn tool_version(dir: &Path, tool: &str) -> Option<String> {
+    return pinned_in(dir).into_iter()
+        .find(|(name, _)| name == tool)
+        .map(|(_, version)| version);
+}
+
+/// Show the tools pinned by asdf/mise in the cwd and its parents, the closest
+/// pin winning, limited to the `tools.allow` list when one is configured.
+pub fn pinned(ctx: &Context) -> Option<String> {
+    let allow = config::strings("tools.allow");
+    let mut tools: Vec<(String, String)> = vec![];
+    for dir in ctx.path.ancestors() {
+        for (tool, version) in pinned_in(dir) {
+            let allowed = allow.is_empty() || allow.contains(&tool.as_str());
+            if allowed && !tools.iter().any(|(name, _)| *name == tool) {
+                tools.push((tool, version));
+            }
+        }
+    }
+    if tools.is_empty() {
+        return None;
+    }
+    let tools: Vec<String> = tools.iter().map(|(tool, version)| format!("{tool} {version}")).collect();
+    return Some(format!("{ICON} {}\x1b[m", tools.join(" ")));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("nodejs 20.11.0\npython 3.12.1 3.11.7 # fallback\n", &[("nodejs", "20.11.0"), ("python", "3.12.1")])]
+    #[case("# comment only\n\n", &[])]
+    #[case("ruby\n", &[])]
+    fn test_parse_tool_versions(#[case] input: &str, #[case] expected: &[(&str, &str)]) {
+        let actual = parse_tool_versions(input);
+        let expected: Vec<(String, String)> = expected.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect();
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("[tools]\nnode = \"20\"\n", &[("node", "20")])]
+    #[case("[tools]\npython = [\"3.12\", \"3.11\"]\n", &[("python", "3.12")])]
+    #[case("[tools]\nerlang = { version = \"26.2\" }\n", &[("erlang", "26.2")])]
+    #[case("[env]\nFOO = \"bar\"\n", &[])]
+    fn test_parse_mise_toml(#[case] input: &str, #[case] expected: &[(&str, &str)]) {
+        let actual = parse_mise_toml(input);
+        let expected: Vec<(String, String)> = expected.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect();
+        assert_eq!(expected, actual)
+    }
+}
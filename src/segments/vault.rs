@@ -0,0 +1,117 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use serde_json::Value;
+
+use crate::status::{cache, config};
+use super::{run_command_output, timeout, Context};
+
+const ICON: &str = "\u{F0483} ";
+/// How long a lookup is trusted before asking the server again.
+const REFRESH_SECS: u64 = 300;
+
+fn token_file() -> Option<PathBuf> {
+    return env::var_os("HOME").map(|home| Path::new(&home).join(".vault-token"));
+}
+
+/// The `data.ttl` seconds from `vault token lookup -format=json`.
+fn parse_lookup(output: &str) -> Option<u64> {
+    let lookup: Value = serde_json::from_str(output).ok()?;
+    return lookup.get("data")?.get("ttl")?.as_u64();
+}
+
+/// Whether vault's error says the server turned the token down, rather
+/// than that it couldn't be reached.
+fn refused(stderr: &str) -> bool {
+    return stderr.contains("permission denied") || stderr.contains("Code: 403");
+}
+
+/// Ask the server about the token, giving up before the segment's deadline
+/// when it's unreachable so the answer is still cached. Yields the expiry
+/// as a unix time, `0` for tokens that never expire, `invalid` when the
+/// lookup is refused, or `unknown` when there's no telling.
+fn lookup() -> String {
+    let client_timeout = format!("{}ms", (timeout("vault") * 3 / 4).as_millis());
+    let Some(output) = run_command_output("vault", &["token", "lookup", "-format=json"], &[("VAULT_CLIENT_TIMEOUT", &client_timeout)]) else {
+        return "unknown".to_owned();
+    };
+    if !output.status.success() {
+        let refused = refused(&String::from_utf8_lossy(&output.stderr));
+        return if refused { "invalid" } else { "unknown" }.to_owned();
+    }
+    return match parse_lookup(&String::from_utf8_lossy(&output.stdout)) {
+        Some(0) => "0".to_owned(),
+        Some(ttl) => (cache::now() + ttl).to_string(),
+        None => "unknown".to_owned(),
+    };
+}
+
+/// Format seconds compactly, e.g. `2d`, `3h12m`, `45m`.
+fn format_ttl(secs: u64) -> String {
+    let (days, hours, minutes) = (secs / 86400, secs % 86400 / 3600, secs % 3600 / 60);
+    if days > 0 {
+        return format!("{days}d");
+    }
+    if hours > 0 {
+        return format!("{hours}h{minutes:02}m");
+    }
+    return format!("{minutes}m");
+}
+
+/// Show whether the local Vault token is still valid and its remaining TTL,
+/// when `vault.enabled` is set and `$VAULT_ADDR` points at a server. A `?`
+/// stands in when the server can't be reached or vault isn't installed.
+pub fn token(_ctx: &Context) -> Option<String> {
+    if !config::bool("vault.enabled", false) {
+        return None;
+    }
+    let addr = env::var("VAULT_ADDR").ok()?;
+    let fingerprint = match env::var("VAULT_TOKEN") {
+        Ok(token) => cache::digest(&token),
+        Err(_) => cache::mtime(&token_file()?)?,
+    };
+    let fingerprint = format!("{fingerprint}:{}", cache::now() / REFRESH_SECS);
+    let expiry = cache::get_or_insert(&format!("vault:{addr}"), &fingerprint, || Some(lookup()))?;
+    if expiry == "unknown" {
+        return Some(format!("\x1b[90m{ICON}?\x1b[m"));
+    }
+    return match expiry.parse::<u64>() {
+        Ok(0) => Some(format!("\x1b[32m{ICON}\u{221E}\x1b[m")),
+        Ok(expiry) if expiry > cache::now() => {
+            let remaining = expiry - cache::now();
+            let color = if remaining < 600 { "\x1b[33m" } else { "\x1b[32m" };
+            Some(format!("{color}{ICON}{}\x1b[m", format_ttl(remaining)))
+        },
+        _ => Some(format!("\x1b[31m{ICON}\u{2717}\x1b[m")),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(r#"{"data": {"ttl": 2763, "policies": ["default"]}}"#, Some(2763))]
+    #[case(r#"{"errors": ["permission denied"]}"#, None)]
+    fn test_parse_lookup(#[case] input: &str, #[case] expected: Option<u64>) {
+        let actual = parse_lookup(input);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("Error looking up token: Code: 403. Errors:\n\n* permission denied", true)]
+    #[case("Error looking up token: dial tcp 127.0.0.1:8200: connect: connection refused", false)]
+    fn test_refused(#[case] stderr: &str, #[case] expected: bool) {
+        let actual = refused(stderr);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case(45 * 60, "45m")]
+    #[case(3 * 3600 + 12 * 60, "3h12m")]
+    #[case(3 * 86400 + 5, "3d")]
+    fn test_format_ttl(#[case] input: u64, #[case] expected: &str) {
+        let actual = format_ttl(input);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,74 @@
+#[cfg(unix)]
+use std::ffi::CStr;
+#[cfg(unix)]
+use std::ptr;
+
+use crate::status::config;
+use super::{Context, glob_match};
+
+const ICON: &str = "\x1b[32m\u{F0499} ";
+const DEFAULT_INTERFACES: &[&str] = &["tun*", "tap*", "wg*", "ppp*", "ipsec*", "nordlynx", "tailscale*", "proton*"];
+
+/// Names of the interfaces that are up, via getifaddrs(3).
+#[cfg(unix)]
+fn up_interfaces() -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+    let mut addrs = ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+        return result;
+    }
+    let mut current = addrs;
+    while !current.is_null() {
+        // SAFETY: getifaddrs returned a valid list which is freed only after the loop
+        let ifaddr = unsafe { &*current };
+        if ifaddr.ifa_flags & libc::IFF_UP as u32 != 0 {
+            let name = unsafe { CStr::from_ptr(ifaddr.ifa_name) }.to_string_lossy().into_owned();
+            // the list has an entry per address family
+            if !result.contains(&name) {
+                result.push(name);
+            }
+        }
+        current = ifaddr.ifa_next;
+    }
+    unsafe { libc::freeifaddrs(addrs) };
+    return result;
+}
+
+#[cfg(not(unix))]
+fn up_interfaces() -> Vec<String> {
+    return vec![];
+}
+
+fn vpn_interfaces(interfaces: Vec<String>, patterns: &[&str]) -> Vec<String> {
+    return interfaces.into_iter()
+        .filter(|name| patterns.iter().any(|pattern| glob_match(pattern, name)))
+        .collect();
+}
+
+/// Show a shield with the names of active VPN interfaces, matched against the
+/// `vpn.interfaces` globs (macOS users will want e.g. `utun4` here, since the
+/// system keeps a few utun interfaces up).
+pub fn indicator(_ctx: &Context) -> Option<String> {
+    let configured = config::strings("vpn.interfaces");
+    let patterns = if configured.is_empty() { DEFAULT_INTERFACES } else { &configured };
+    let vpns = vpn_interfaces(up_interfaces(), patterns);
+    if vpns.is_empty() {
+        return None;
+    }
+    return Some(format!("{ICON}{}\x1b[m", vpns.join(",")));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(&["lo", "eth0", "wg-office"], DEFAULT_INTERFACES, &["wg-office"])]
+    #[case(&["lo", "utun0", "utun4"], &["utun4"], &["utun4"])]
+    #[case(&["lo", "docker0"], DEFAULT_INTERFACES, &[])]
+    fn test_vpn_interfaces(#[case] interfaces: &[&str], #[case] patterns: &[&str], #[case] expected: &[&str]) {
+        let actual = vpn_interfaces(interfaces.iter().map(|name| name.to_string()).collect(), patterns);
+        assert_eq!(expected, actual)
+    }
+}
@@ -0,0 +1,223 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use toml::Value;
+use wasmtime::{Caller, Config, Engine, Extern, Instance, Linker, Module, Store};
+
+use crate::status::{cache, config};
+use super::{Context, Registry, Segment, StyledText, installed, run_with_timeout};
+use super::plugin::context_json;
+
+/// The instructions a plugin may run per prompt before it's stopped, so a
+/// spinning plugin can't keep its thread busy after the deadline.
+const FUEL: u64 = 50_000_000;
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// What a plugin is allowed to reach through its host functions.
+struct Host {
+    path: PathBuf,
+    /// Programs the plugin may run, from its `commands` setting.
+    commands: Vec<String>,
+}
+
+/// A segment rendered by a WASM module, sandboxed so shared plugins can be
+/// used without trusting them with native code. There's no WASI: besides
+/// its own memory, a plugin only has the host functions it imports from
+/// the `statusline` module, which read the environment, read the directory
+/// the prompt is for, or run a program its config allows.
+///
+/// Strings cross as a pointer and length into the plugin's memory. The
+/// plugin exports `memory`, `alloc(len) -> ptr` for strings coming in, and
+/// `render(ptr, len) -> i64` taking the context JSON. Returned strings are
+/// packed as `ptr << 32 | len`, with 0 meaning nothing:
+///
+/// - `env(name_ptr, name_len) -> i64`
+/// - `cwd() -> i64`
+/// - `run(argv_ptr, argv_len) -> i64`, argv being a JSON array of strings
+pub struct Wasm {
+    name: String,
+    engine: Engine,
+    module: Module,
+    commands: Vec<String>,
+}
+
+fn pack(ptr: i32, len: usize) -> i64 {
+    return ((ptr as u32 as i64) << 32) | len as u32 as i64;
+}
+
+fn unpack(packed: i64) -> (usize, usize) {
+    return ((packed as u64 >> 32) as usize, packed as u32 as usize);
+}
+
+fn memory(caller: &mut Caller<'_, Host>) -> Option<wasmtime::Memory> {
+    return caller.get_export("memory").and_then(Extern::into_memory);
+}
+
+fn read_string(caller: &mut Caller<'_, Host>, ptr: i32, len: i32) -> Option<String> {
+    let mut buffer = vec![0; usize::try_from(len).ok()?];
+    memory(caller)?.read(&*caller, usize::try_from(ptr).ok()?, &mut buffer).ok()?;
+    return String::from_utf8(buffer).ok();
+}
+
+/// Copy `value` into memory allocated by the plugin.
+fn write_string(caller: &mut Caller<'_, Host>, value: Option<String>) -> i64 {
+    let Some(value) = value else {
+        return 0;
+    };
+    let Some(alloc) = caller.get_export("alloc").and_then(Extern::into_func) else {
+        return 0;
+    };
+    let Ok(alloc) = alloc.typed::<i32, i32>(&*caller) else {
+        return 0;
+    };
+    let Ok(ptr) = alloc.call(&mut *caller, value.len() as i32) else {
+        return 0;
+    };
+    let Some(memory) = memory(caller) else {
+        return 0;
+    };
+    if memory.write(&mut *caller, ptr as usize, value.as_bytes()).is_err() {
+        return 0;
+    }
+    return pack(ptr, value.len());
+}
+
+/// Run a command for the plugin, if its program is one it was granted.
+fn run(host: &Host, argv: &str) -> Option<String> {
+    let argv: Vec<String> = serde_json::from_str(argv).ok()?;
+    let (program, args) = argv.split_first()?;
+    if !host.commands.contains(program) || !installed(program) {
+        return None;
+    }
+    let mut command = Command::new(program);
+    command.args(args).current_dir(&host.path);
+    let timeout = config::value("plugins.timeout").and_then(config::duration).unwrap_or(DEFAULT_TIMEOUT);
+    return run_with_timeout(command, timeout);
+}
+
+fn linker(engine: &Engine) -> wasmtime::Result<Linker<Host>> {
+    let mut linker = Linker::new(engine);
+    linker.func_wrap("statusline", "env", |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> i64 {
+        let value = read_string(&mut caller, ptr, len).and_then(|name| env::var(name).ok());
+        return write_string(&mut caller, value);
+    })?;
+    linker.func_wrap("statusline", "cwd", |mut caller: Caller<'_, Host>| -> i64 {
+        let path = caller.data().path.to_string_lossy().into_owned();
+        return write_string(&mut caller, Some(path));
+    })?;
+    linker.func_wrap("statusline", "run", |mut caller: Caller<'_, Host>, ptr: i32, len: i32| -> i64 {
+        let output = read_string(&mut caller, ptr, len).and_then(|argv| run(caller.data(), &argv));
+        return write_string(&mut caller, output);
+    })?;
+    return Ok(linker);
+}
+
+/// Compiling is far slower than a prompt can afford, so the compiled
+/// module is kept in the cache until the `.wasm` file changes.
+fn compile(engine: &Engine, path: &Path) -> Option<Module> {
+    let fingerprint = cache::mtime(path)?;
+    let compiled = cache::cache_file(&format!("wasm:{}:{fingerprint}", path.display()))?;
+    if compiled.exists() {
+        // SAFETY: the file is one we serialized into our own cache dir, and
+        // wasmtime rejects artifacts from another version or configuration.
+        if let Ok(module) = unsafe { Module::deserialize_file(engine, &compiled) } {
+            return Some(module);
+        }
+    }
+    let module = Module::from_file(engine, path).ok()?;
+    if let Ok(bytes) = module.serialize() {
+        if let Some(dir) = compiled.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(&compiled, bytes);
+    }
+    return Some(module);
+}
+
+impl Wasm {
+    fn open(name: &str, settings: &Value) -> Option<Wasm> {
+        let path = settings.get("path")?.as_str()?;
+        let commands = settings.get("commands").and_then(Value::as_array)
+            .map(|commands| commands.iter().filter_map(Value::as_str).map(str::to_owned).collect())
+            .unwrap_or_default();
+        let mut engine_config = Config::new();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config).ok()?;
+        let module = compile(&engine, Path::new(path))?;
+        return Some(Wasm{name: name.to_owned(), engine, module, commands});
+    }
+
+    fn call(&self, ctx: &Context) -> wasmtime::Result<Option<String>> {
+        let host = Host{path: ctx.path.clone(), commands: self.commands.clone()};
+        let mut store = Store::new(&self.engine, host);
+        store.set_fuel(FUEL)?;
+        let instance: Instance = linker(&self.engine)?.instantiate(&mut store, &self.module)?;
+        let memory = instance.get_memory(&mut store, "memory").ok_or_else(|| wasmtime::format_err!("no memory export"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let render = instance.get_typed_func::<(i32, i32), i64>(&mut store, "render")?;
+
+        let input = context_json(&self.name, ctx);
+        let ptr = alloc.call(&mut store, input.len() as i32)?;
+        memory.write(&mut store, ptr as usize, input.as_bytes())?;
+        let output = render.call(&mut store, (ptr, input.len() as i32))?;
+        if output == 0 {
+            return Ok(None);
+        }
+        let (ptr, len) = unpack(output);
+        let mut buffer = vec![0; len];
+        memory.read(&store, ptr, &mut buffer)?;
+        return Ok(Some(String::from_utf8_lossy(&buffer).into_owned()));
+    }
+}
+
+impl Segment for Wasm {
+    fn name(&self) -> &str {
+        return &self.name;
+    }
+
+    fn render(&self, ctx: &Context) -> Option<StyledText> {
+        let output = self.call(ctx).ok()??;
+        let output = output.lines().next().unwrap_or_default().trim_end();
+        if output.is_empty() {
+            return None;
+        }
+        return Some(StyledText::from_ansi(output));
+    }
+
+    fn slow(&self) -> bool {
+        return true;
+    }
+}
+
+/// Load the modules in `[plugins.wasm.<name>]` tables, each with a `path`
+/// to the `.wasm` file and the `commands` it may run; ones that fail to
+/// compile are skipped.
+pub fn load() -> Registry {
+    let mut registry = Registry::default();
+    let Some(plugins) = config::value("plugins.wasm").and_then(Value::as_table) else {
+        return registry;
+    };
+    for (name, settings) in plugins {
+        if let Some(wasm) = Wasm::open(name, settings) {
+            registry.register(wasm);
+        }
+    }
+    return registry;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0, 0)]
+    #[case(1024, 5)]
+    #[case(i32::MAX, 0x7fff_ffff)]
+    fn test_pack(#[case] ptr: i32, #[case] len: usize) {
+        let actual = unpack(pack(ptr, len));
+        assert_eq!((ptr as usize, len), actual)
+    }
+}
@@ -0,0 +1,87 @@
+use crate::status::{cache, config};
+use super::{printable, run_command, Context};
+
+const KEY: &str = "weather";
+/// How many seconds curl is given for the whole request.
+const TIMEOUT: &str = "5";
+const USER_AGENT: &str = concat!("statusline/", env!("CARGO_PKG_VERSION"));
+/// The most characters of a forecast shown, which is a glyph and a
+/// temperature when all is well.
+const MAX_LENGTH: usize = 24;
+
+/// Percent-encode a location for the wttr.in path, keeping `+` as wttr's space.
+fn encode_location(location: &str) -> String {
+    let mut result = String::new();
+    for byte in location.trim().bytes() {
+        match byte {
+            b' ' => result.push('+'),
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b',' | b'+' | b'@' => result.push(byte as char),
+            _ => result += &format!("%{byte:02X}"),
+        }
+    }
+    return result;
+}
+
+/// The forecast in a response body, as text that's safe to print: it comes
+/// from the network, so anything that could drive the terminal is dropped
+/// and it's cut short.
+fn parse_body(body: &str) -> Option<String> {
+    let body = printable(&body.split_whitespace().collect::<Vec<&str>>().join(" "));
+    if body.is_empty() || body.contains("Unknown location") || body.starts_with('<') {
+        return None;
+    }
+    return Some(body.chars().take(MAX_LENGTH).collect());
+}
+
+/// Ask wttr.in over HTTPS, through curl, which fails on an error status.
+fn fetch(location: &str) -> Option<String> {
+    let url = format!("https://wttr.in/{}?format=%c%t", encode_location(location));
+    let body = run_command("curl", &["--silent", "--fail", "--max-time", TIMEOUT, "--user-agent", USER_AGENT, &url])?;
+    return parse_body(&body);
+}
+
+/// Fetch the weather for `weather.location`; run in the background.
+pub fn refresh() {
+    let location = config::string("weather.location").unwrap_or_default();
+    if let Some(weather) = fetch(location) {
+        cache::store_refreshed(KEY, &weather);
+    }
+}
+
+/// Show the condition glyph and temperature from wttr.in, fetched with curl, when
+/// `weather.enabled` is set, refreshed in the background every `weather.ttl`
+/// seconds (default half an hour).
+pub fn forecast(_ctx: &Context) -> Option<String> {
+    if !config::bool("weather.enabled", false) {
+        return None;
+    }
+    let ttl = u64::try_from(config::integer("weather.ttl", 1800)).unwrap_or(1800);
+    return cache::refreshed(KEY, ttl, "weather");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("London", "London")]
+    #[case("San Francisco", "San+Francisco")]
+    #[case("Zürich", "Z%C3%BCrich")]
+    #[case("", "")]
+    fn test_encode_location(#[case] input: &str, #[case] expected: &str) {
+        let actual = encode_location(input);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("\u{2600}\u{FE0F}   +12\u{B0}C\n", Some("\u{2600}\u{FE0F} +12\u{B0}C"))]
+    #[case("Unknown location; please try ~51.5,-0.1", None)]
+    #[case("\x1b]0;pwned\x07+12\u{B0}C", Some("]0;pwned+12\u{B0}C"))]
+    #[case("<html><body>captive portal</body></html>", None)]
+    #[case("+12°C and then a very long rant", Some("+12°C and then a very lo"))]
+    fn test_parse_body(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_body(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+}
@@ -0,0 +1,48 @@
+#[cfg(unix)]
+use std::ffi::CString;
+#[cfg(unix)]
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use super::Context;
+
+pub const ICON: &str = "\x1b[31m\u{F023}\x1b[m";
+
+/// Whether the current user may create files in `path`; access(2) also
+/// reports read-only mounts, which a permission bit check would miss.
+#[cfg(unix)]
+pub fn is_writable(path: &Path) -> bool {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return true;
+    };
+    return unsafe { libc::access(path.as_ptr(), libc::W_OK) } == 0;
+}
+
+/// Whether the current user may `cd` into `path`.
+#[cfg(unix)]
+pub fn is_enterable(path: &Path) -> bool {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return true;
+    };
+    return unsafe { libc::access(path.as_ptr(), libc::X_OK) } == 0;
+}
+
+/// Elsewhere a directory that can be listed can be entered.
+#[cfg(not(unix))]
+pub fn is_enterable(path: &Path) -> bool {
+    return std::fs::read_dir(path).is_ok();
+}
+
+/// Elsewhere only the read-only attribute is checked.
+#[cfg(not(unix))]
+pub fn is_writable(path: &Path) -> bool {
+    return std::fs::metadata(path).map_or(true, |meta| !meta.permissions().readonly());
+}
+
+/// Show a lock when the cwd isn't writable.
+pub fn indicator(ctx: &Context) -> Option<String> {
+    if is_writable(&ctx.path) {
+        return None;
+    }
+    return Some(ICON.to_owned());
+}
@@ -0,0 +1,38 @@
+use std::env;
+use std::fs;
+
+use super::Context;
+
+const ICON: &str = "\x1b[34m\u{F17A}";
+
+/// Whether a `/proc/version` string comes from a WSL kernel.
+fn wsl_kernel(version: &str) -> bool {
+    let version = version.to_lowercase();
+    return version.contains("microsoft") || version.contains("wsl");
+}
+
+/// Show a Windows marker and the distro name under Windows Subsystem for Linux.
+pub fn indicator(_ctx: &Context) -> Option<String> {
+    if let Ok(distro) = env::var("WSL_DISTRO_NAME") {
+        return Some(format!("{ICON} {distro}\x1b[m"));
+    }
+    if wsl_kernel(&fs::read_to_string("/proc/version").ok()?) {
+        return Some(format!("{ICON} WSL\x1b[m"));
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("Linux version 5.15.133.1-microsoft-standard-WSL2 (root@1c602f52c2e4)", true)]
+    #[case("Linux version 4.4.0-19041-Microsoft (Microsoft@Microsoft.com)", true)]
+    #[case("Linux version 6.6.8-arch1-1 (linux@archlinux)", false)]
+    fn test_wsl_kernel(#[case] input: &str, #[case] expected: bool) {
+        let actual = wsl_kernel(input);
+        assert_eq!(expected, actual)
+    }
+}
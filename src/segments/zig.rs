@@ -0,0 +1,42 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::sync::LazyLock;
+use regex::Regex;
+
+use super::{Context, cached_output, project, version_cmp};
+
+const ICON: &str = "\x1b[38;5;214m\u{E6A9}";
+
+static MINIMUM_VERSION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\.minimum_zig_version\s*=\s*"([^"]+)""#).unwrap());
+
+/// Read `.minimum_zig_version = "0.11.0"` from a `build.zig.zon`.
+fn parse_minimum_version(contents: &str) -> Option<String> {
+    return MINIMUM_VERSION.captures(contents).map(|caps| caps[1].to_owned());
+}
+
+/// Show the zig in use for `build.zig` projects, in red when it's older than
+/// the `build.zig.zon` minimum.
+pub fn version(ctx: &Context) -> Option<String> {
+    let build = ctx.find_upwards(&project::files("zig"))?;
+    let installed = cached_output("zig", &["version"])?;
+    let minimum = fs::read_to_string(build.with_file_name("build.zig.zon")).ok()
+        .and_then(|contents| parse_minimum_version(&contents));
+    if minimum.is_some_and(|minimum| version_cmp(&installed, &minimum) == Ordering::Less) {
+        return Some(format!("{ICON}\x1b[91m{installed}\x1b[m"));
+    }
+    return Some(format!("{ICON}{installed}\x1b[m"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(".{\n    .name = \"app\",\n    .version = \"0.1.0\",\n    .minimum_zig_version = \"0.12.0\",\n}", Some("0.12.0"))]
+    #[case(".{\n    .name = \"app\",\n    .version = \"0.1.0\",\n}", None)]
+    fn test_parse_minimum_version(#[case] input: &str, #[case] expected: Option<&str>) {
+        let actual = parse_minimum_version(input);
+        assert_eq!(expected.map(str::to_owned), actual)
+    }
+}
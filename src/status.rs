@@ -1,47 +1,830 @@
+#[path = "bench.rs"] pub mod bench;
+#[path = "cache.rs"] pub mod cache;
+#[path = "config.rs"] pub mod config;
+#[cfg(unix)]
+#[path = "daemon.rs"] pub mod daemon;
+#[path = "explain.rs"] pub mod explain;
 #[path = "git.rs"] pub mod git;
+#[path = "log.rs"] pub mod log;
+#[path = "report.rs"] pub mod report;
+#[path = "segments/mod.rs"] pub mod segments;
+#[path = "style.rs"] pub mod style;
+#[cfg(all(unix, feature = "watch"))]
+#[path = "watch.rs"] pub mod watch;
+#[path = "builder.rs"] pub mod builder;
 
+use std::cmp::Reverse;
+#[cfg(unix)]
+use std::collections::HashMap;
 use std::env;
-use regex::Regex;
+#[cfg(unix)]
+use std::ffi::{c_char, CStr, CString};
+use std::fs;
+use std::io;
+#[cfg(unix)]
+use std::mem::MaybeUninit;
+use std::panic;
+use std::path::{Path, PathBuf};
+#[cfg(unix)]
+use std::ptr;
+#[cfg(unix)]
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::Duration;
+use toml::Value;
+use unicode_segmentation::UnicodeSegmentation;
 
-fn minify_dir(name: &str) -> String {
-    let regexp = Regex::new(r"(\W*\w)").unwrap();
-    if let Some(mat) = regexp.find(name) {
-        return name[mat.start()..mat.end()].to_owned();
+use report::StatusReport;
+use segments::{Registry, Segment, StyledText, Timing};
+use style::{Color, Style};
+
+fn is_word(grapheme: &str) -> bool {
+    return grapheme.chars().next().is_some_and(|c| c.is_alphanumeric() || c == '_');
+}
+
+/// The leading punctuation of `name` and the next `length` graphemes,
+/// whole graphemes at a time so accents and emoji sequences aren't split.
+/// With `count_dots` the punctuation, like a hidden directory's dot, counts
+/// towards `length`, though at least one letter is always kept.
+fn minify_dir_to(name: &str, length: usize, count_dots: bool) -> String {
+    let mut kept = 0;
+    let mut in_word = false;
+    for (start, grapheme) in name.grapheme_indices(true) {
+        in_word |= is_word(grapheme);
+        if in_word || count_dots {
+            kept += 1;
+        }
+        if in_word && kept >= length {
+            return name[..start + grapheme.len()].to_owned();
+        }
     }
     return name.to_owned();
 }
 
-fn minify_path(path: &str, keep: usize) -> String {
+/// `name` shortened to `path.dir_length` graphemes, 1 by default, not
+/// counting leading dots unless `path.count_dots` is set.
+fn minify_dir(name: &str) -> String {
+    let length = usize::try_from(config::integer("path.dir_length", 1)).unwrap_or(1).max(1);
+    return minify_dir_to(name, length, config::bool("path.count_dots", false));
+}
+
+/// `path` with `with` in place of `prefix`, if it's a whole-name prefix.
+fn replace_prefix(path: &str, prefix: &str, with: &str) -> Option<String> {
+    let rest = path.strip_prefix(prefix)?;
+    if !rest.is_empty() && !rest.starts_with('/') {
+        return None;
+    }
+    return Some(format!("{with}{rest}"));
+}
+
+/// The lowest uid of a login user, `UID_MIN` in a login.defs line like
+/// `UID_MIN   1000`.
+#[cfg(unix)]
+fn parse_uid_min(defs: &str) -> Option<u32> {
+    return defs.lines().find_map(|line| match line.split_whitespace().collect::<Vec<&str>>()[..] {
+        ["UID_MIN", uid, ..] => uid.parse().ok(),
+        _ => None,
+    });
+}
+
+/// The lowest uid of a login user, from /etc/login.defs where there is
+/// one. Those below it but root are system accounts, like `bin` whose
+/// "home" is just a system directory.
+#[cfg(unix)]
+fn uid_min() -> u32 {
+    static UID_MIN: OnceLock<u32> = OnceLock::new();
+    return *UID_MIN.get_or_init(|| {
+        let defs = fs::read_to_string("/etc/login.defs").unwrap_or_default();
+        return parse_uid_min(&defs).unwrap_or(if cfg!(target_os = "macos") { 501 } else { 1000 });
+    });
+}
+
+/// The home directory of a login user, skipping system accounts and any
+/// whose home is `/` or missing, as with `nobody`.
+#[cfg(unix)]
+fn lookup_home(name: &str) -> Option<String> {
+    let name = CString::new(name).ok()?;
+    let mut passwd = MaybeUninit::<libc::passwd>::uninit();
+    let mut buffer = vec![0 as c_char; 4096];
+    let mut result = ptr::null_mut();
+    let status = unsafe {
+        libc::getpwnam_r(name.as_ptr(), passwd.as_mut_ptr(), buffer.as_mut_ptr(), buffer.len(), &mut result)
+    };
+    if status != 0 || result.is_null() {
+        return None;
+    }
+    let passwd = unsafe { passwd.assume_init() };
+    if passwd.pw_uid != 0 && passwd.pw_uid < uid_min() {
+        return None;
+    }
+    let dir = unsafe { CStr::from_ptr(passwd.pw_dir) };
+    return dir.to_str().ok().filter(|dir| *dir != "/" && Path::new(dir).is_dir()).map(str::to_owned);
+}
+
+/// The home directory of a login user, looked up once a process, as the
+/// names at the start of every path outside the home are tried.
+#[cfg(unix)]
+fn user_home(name: &str) -> Option<String> {
+    static HOMES: LazyLock<Mutex<HashMap<String, Option<String>>>> = LazyLock::new(Mutex::default);
+    let mut homes = HOMES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    return homes.entry(name.to_owned()).or_insert_with(|| lookup_home(name)).clone();
+}
+
+#[cfg(not(unix))]
+fn user_home(_name: &str) -> Option<String> {
+    return None;
+}
+
+fn tilde_with(path: &str, home: Option<&str>, user_home: impl Fn(&str) -> Option<String>) -> String {
+    if let Some(home) = home.map(|home| home.trim_end_matches('/')).filter(|home| !home.is_empty()) {
+        if let Some(path) = replace_prefix(path, home, "~") {
+            return path;
+        }
+    }
+    // other users' homes are a level or two down, e.g. /root or /home/alice
+    let mut prefix = String::new();
+    for name in path.split('/').skip(1).take(3).take_while(|name| !name.is_empty()) {
+        prefix = format!("{prefix}/{name}");
+        if user_home(name).as_deref() == Some(prefix.as_str()) {
+            return replace_prefix(path, &prefix, &format!("~{name}")).unwrap_or_else(|| path.to_owned());
+        }
+    }
+    return path.to_owned();
+}
+
+/// The part of a Windows path that's never shortened, a drive like `C:` or
+/// a UNC `\\server\share`, without any `\\?\` verbatim marker, followed
+/// by the rest of the path.
+fn split_prefix(path: &str) -> (String, &str) {
+    let unc = path.strip_prefix(r"\\?\UNC\")
+        .or_else(|| path.strip_prefix(r"\\").filter(|rest| !rest.starts_with(r"?\")));
+    if let Some(unc) = unc {
+        let end = unc.match_indices('\\').nth(1).map_or(unc.len(), |(i, _)| i);
+        return (format!(r"\\{}", &unc[..end]), &unc[end..]);
+    }
+    let local = path.strip_prefix(r"\\?\").unwrap_or(path);
+    if local.len() >= 2 && local.as_bytes()[0].is_ascii_alphabetic() && local.as_bytes()[1] == b':' {
+        return (local[..2].to_owned(), &local[2..]);
+    }
+    return (String::new(), path);
+}
+
+/// `path` as MSYS2 and Git Bash write it, e.g. `/c/Users` for `C:\Users`.
+fn msys_path(path: &str) -> String {
+    let (prefix, rest) = split_prefix(path);
+    let rest = rest.replace('\\', "/");
+    if let Some(drive) = prefix.strip_suffix(':') {
+        return format!("/{}{rest}", drive.to_ascii_lowercase());
+    }
+    return format!("{}{rest}", prefix.replace('\\', "/"));
+}
+
+/// `path` in the form the shell shows it: Windows paths are converted
+/// under MSYS2 and Git Bash, which set `$MSYSTEM`.
+fn shell_form(path: &str) -> String {
+    if env::var_os("MSYSTEM").is_some() {
+        return msys_path(path);
+    }
+    return path.to_owned();
+}
+
+/// `path` with the home directory shown as `~`, or `~user` for another
+/// user's home.
+fn tilde(path: &str) -> String {
+    let home = env::var("HOME").or_else(|_| env::var("USERPROFILE")).ok().map(|home| shell_form(&home));
+    return tilde_with(&shell_form(path), home.as_deref(), user_home);
+}
+
+/// Glyphs for well-known directories, used with `path.icons = true`.
+const DEFAULT_ICONS: &[(&str, &str)] = &[
+    ("~", "\u{2302}"),
+    ("~/Documents", "\u{F0219}"),
+    ("~/Downloads", "\u{F01DA}"),
+    ("/tmp", "\u{F017}"),
+    ("node_modules", "\u{E718}"),
+    (".git", "\u{E702}"),
+];
+
+/// The directories shown as glyphs, from `[path.icons]` or the defaults
+/// when `path.icons = true`, each keyed by a path like `"~/Documents"` or
+/// by a name like `node_modules` standing for a directory of that name
+/// anywhere.
+fn icons() -> Vec<(String, String)> {
+    let Some(icons) = config::value("path.icons") else {
+        return vec![];
+    };
+    if let Some(table) = icons.as_table() {
+        return table.iter()
+            .filter_map(|(dir, icon)| Some((dir.to_owned(), icon.as_str()?.to_owned())))
+            .collect();
+    }
+    if icons.as_bool() != Some(true) {
+        return vec![];
+    }
+    return DEFAULT_ICONS.iter().map(|&(dir, icon)| (dir.to_owned(), icon.to_owned())).collect();
+}
+
+fn is_icon_path(dir: &str) -> bool {
+    return dir.starts_with('~') || dir.contains(['/', '\\']);
+}
+
+/// Named directories from `[path.aliases]`, e.g. `work = "~/src/company"`,
+/// as `~name` and the directory, and the paths given icons, as the icon
+/// and the directory, longest first so nested ones win.
+fn aliases() -> Vec<(String, String)> {
+    let mut aliases: Vec<(String, String)> = icons().into_iter()
+        .filter(|(dir, _)| is_icon_path(dir))
+        .map(|(dir, icon)| (icon, tilde(dir.trim_end_matches('/'))))
+        .collect();
+    if let Some(table) = config::value("path.aliases").and_then(Value::as_table) {
+        aliases.extend(table.iter().filter_map(|(name, dir)| {
+            let dir = tilde(dir.as_str()?.trim_end_matches('/'));
+            return Some((format!("~{}", name.trim_start_matches('~')), dir));
+        }));
+    }
+    aliases.sort_by_key(|(_, dir)| Reverse(dir.len()));
+    return aliases;
+}
+
+/// The icon standing for a directory called `name`, wherever it is.
+fn name_icon<'a>(name: &str, icons: &'a [(String, String)]) -> Option<&'a str> {
+    return icons.iter()
+        .find(|(dir, _)| !is_icon_path(dir) && dir == name)
+        .map(|(_, icon)| icon.as_str());
+}
+
+fn alias_with(path: &str, aliases: &[(String, String)]) -> String {
+    for (name, dir) in aliases {
+        if let Some(path) = replace_prefix(path, dir, name) {
+            return path;
+        }
+    }
+    return path.to_owned();
+}
+
+/// `path` starting from the closest named directory or home, as zsh
+/// would show it.
+fn named_path(path: &str) -> String {
+    return alias_with(&tilde(path), &aliases());
+}
+
+/// The shortest prefix of `name`, no shorter than `minify_dir` keeps,
+/// that none of its `siblings` start with, so it can still be tab-completed.
+fn unique_prefix(name: &str, siblings: &[String]) -> String {
+    let shortest = minify_dir(name).len();
+    let mut ends = name.grapheme_indices(true).map(|(start, grapheme)| start + grapheme.len());
+    let end = ends.find(|&end| {
+        let prefix = &name[..end];
+        return end >= shortest && !siblings.iter().any(|sibling| sibling != name && sibling.starts_with(prefix));
+    });
+    return name[..end.unwrap_or(name.len())].to_owned();
+}
+
+/// The directories next to `dir`, for `path.unique`.
+fn siblings(dir: &Path) -> Option<Vec<String>> {
+    let entries = fs::read_dir(dir.parent()?).ok()?;
+    return Some(entries.flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect());
+}
+
+/// `name` cut down to `max` graphemes with a `…` in the middle, keeping
+/// the start and end which usually tell directories apart.
+fn truncate_middle(name: &str, max: usize) -> String {
+    let graphemes: Vec<&str> = name.graphemes(true).collect();
+    if max == 0 || graphemes.len() <= max {
+        return name.to_owned();
+    }
+    let kept = max - 1;
+    let tail = kept / 2;
+    let head = kept - tail;
+    return format!("{}\u{2026}{}", graphemes[..head].concat(), graphemes[graphemes.len() - tail..].concat());
+}
+
+/// Whether `name` matches one of the `path.never_abbreviate` patterns,
+/// e.g. `src` or `*-prod`, which are always shown whole.
+fn never_abbreviated(name: &str, patterns: &[&str]) -> bool {
+    return patterns.iter().any(|pattern| segments::glob_match(pattern, name));
+}
+
+/// The style of each of `dirs`, from the permissions on the directories
+/// they name: `path.dim_unenterable` grays out the ones the user can't
+/// enter, and `path.readonly` colors the last one when it isn't writable,
+/// or with `"lock"` returns a lock to put after it. Only the directory the
+/// prompt is for is checked for writing.
+fn permission_styles(dirs: &[Option<&Path>], style: Style, tip: Tip) -> (Vec<Style>, &'static str) {
+    let mut styles = vec![style; dirs.len()];
+    if config::bool("path.dim_unenterable", false) {
+        for (i, dir) in dirs.iter().enumerate() {
+            if dir.is_some_and(|dir| !segments::writable::is_enterable(dir)) {
+                styles[i] = Style::fg(Color::GRAY);
+            }
+        }
+    }
+    let last = dirs.last().copied().flatten().filter(|_| tip.cwd);
+    let readonly = config::string("path.readonly")
+        .filter(|_| last.is_some_and(|last| !segments::writable::is_writable(last)));
+    let Some(readonly) = readonly else {
+        return (styles, "");
+    };
+    if readonly == "lock" {
+        return (styles, segments::writable::ICON);
+    }
+    if let (Some(color), Some(style)) = (Color::parse(readonly), styles.last_mut()) {
+        style.fg = Some(color);
+    }
+    return (styles, "");
+}
+
+/// A repository's name is drawn bold unless `path.emphasize_repo` is off.
+fn repo_style(style: Style) -> Style {
+    if !config::bool("path.emphasize_repo", true) {
+        return style;
+    }
+    return style.bold();
+}
+
+/// With `path.worktree_names` set, a linked worktree's root is named after
+/// the repository it belongs to, as `app⎇fix-123`, in place of its path.
+fn worktree_label(root: &str) -> Option<String> {
+    if !config::bool("path.worktree_names", false) {
+        return None;
+    }
+    let (main, name) = git::worktree(Path::new(root))?;
+    return Some(format!("{main}\u{2387}{name}"));
+}
+
+/// The colors `path.gradient` runs the path's names through, from the
+/// first to the last: a list of colors, or `true` to brighten from a
+/// darker shade of the path's own color.
+fn gradient_colors(style: Style) -> Vec<Color> {
+    return match config::value("path.gradient") {
+        Some(Value::Boolean(true)) => style.fg.map(|fg| vec![Color::Ansi(0).mix(fg, 0.5), fg]).unwrap_or_default(),
+        Some(Value::Array(_)) => config::strings("path.gradient").into_iter().filter_map(Color::parse).collect(),
+        _ => vec![],
+    };
+}
+
+/// The color `t` of the way along `colors`, 0 to 1.
+fn gradient_at(colors: &[Color], t: f64) -> Color {
+    if colors.len() < 2 {
+        return colors[0];
+    }
+    let scaled = t.clamp(0.0, 1.0) * (colors.len() - 1) as f64;
+    let i = (scaled as usize).min(colors.len() - 2);
+    return colors[i].mix(colors[i + 1], scaled - i as f64);
+}
+
+/// Recolor the names in `text` drawn in the path's `style` along the
+/// gradient, one color per name, each with the separator after it. Names
+/// styled otherwise, as read-only or unenterable, and the VCS status
+/// between them keep their colors but still count towards the depth.
+fn apply_gradient(text: StyledText, style: Style, colors: &[Color]) -> StyledText {
+    if colors.is_empty() {
+        return text;
+    }
+    let separator = |name: &str| name == "/" || name == "\\";
+    // a separator on its own, as after a repo's status, goes with the name before it
+    let total = text.spans.iter()
+        .filter(|span| span.style.fg == style.fg)
+        .flat_map(|span| span.text.split_inclusive(['/', '\\']))
+        .enumerate()
+        .filter(|&(i, name)| i == 0 || !separator(name))
+        .count();
+    let mut result = StyledText::new();
+    let mut depth = 0;
+    for span in text.spans {
+        if span.style.fg != style.fg {
+            result = result.push(span.style, &span.text);
+            continue;
+        }
+        for name in span.text.split_inclusive(['/', '\\']) {
+            let shared = depth > 0 && separator(name);
+            let position = if shared { depth - 1 } else { depth };
+            let t = position as f64 / total.saturating_sub(1).max(1) as f64;
+            result = result.push(Style{fg: Some(gradient_at(colors, t)), ..span.style}, name);
+            if !shared {
+                depth += 1;
+            }
+        }
+    }
+    return result;
+}
+
+/// What the last name of a path passed to `minify_path` stands for.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Tip {
+    /// The directory the prompt is for.
+    cwd: bool,
+    /// The root of a repository.
+    repo: bool,
+}
+
+impl Tip {
+    const CWD: Tip = Tip{cwd: true, repo: false};
+}
+
+/// With more than `depth` names, drop the ones after the first, which
+/// says where the path starts, until `depth` are left, putting how many
+/// were hidden in their place, as in `~/…3…/project/src`. A single name
+/// isn't worth the marker that would replace it.
+fn elide(names: &mut Vec<String>, styles: &mut Vec<Style>, depth: usize, style: Style) {
+    if depth < 2 || names.len() < depth + 2 {
+        return;
+    }
+    let hidden = names.len() - depth;
+    names.splice(1..1 + hidden, [format!("\u{2026}{hidden}\u{2026}")]);
+    styles.splice(1..1 + hidden, [style]);
+}
+
+/// `names` joined by `separator` after `prefix`, each in its style, with
+/// a separator taking the style of the name before it.
+fn paint_names(prefix: String, names: &[String], separator: &str, styles: &[Style]) -> String {
+    let mut result = String::new();
+    let mut run = prefix;
+    for (i, name) in names.iter().enumerate() {
+        if i > 0 {
+            run += separator;
+            if styles[i] != styles[i - 1] {
+                result += &styles[i - 1].paint(&run);
+                run.clear();
+            }
+        }
+        run += name;
+    }
+    return result + &styles.last().copied().unwrap_or_default().paint(&run);
+}
+
+/// Shorten all but the last `keep` names of `path`, and those to
+/// `path.max_length` if that's set. `real` is the directory
+/// `path` displays, if it's on disk, which lines up with it name for name
+/// from the end; with `path.unique` set, names are shortened only as far
+/// as they're still distinct from the other directories beside them.
+/// `tip` says what the last name is, a repository's name being emphasized.
+fn minify_path(path: &str, keep: usize, style: Style, real: Option<&Path>, tip: Tip) -> String {
     let mut result: Vec<String> = vec![];
-    // if let Some(home_path) = env::home_dir() {
-    //     if let Some(home) = home_path.to_str() {
-    //         path = path.replace(home, "~");
-    //     }
-    // }
-    let dirs: Vec<&str> = path.split("/").collect();
-    let limit = dirs.len() - keep;
+    // a drive or share stays as it is, and Windows paths keep their backslashes
+    let (prefix, path) = split_prefix(path);
+    let separator = if !path.contains('/') && path.contains('\\') { "\\" } else { "/" };
+    let dirs: Vec<&str> = path.split(separator).collect();
+    let real_dirs: Vec<Option<&Path>> = (0..dirs.len())
+        .map(|i| real.and_then(|real| real.ancestors().nth(dirs.len() - 1 - i)))
+        .collect();
+    let limit = dirs.len().saturating_sub(keep);
+    // a named directory is only worth it if its name can still be read
+    let aliased = dirs.first().is_some_and(|first| aliases().iter().any(|(name, _)| name == first));
+    let unique = config::bool("path.unique", false);
+    let whole = config::strings("path.never_abbreviate");
+    let max_length = usize::try_from(config::integer("path.max_length", 0)).unwrap_or(0);
+    let icons = icons();
     for (i, name) in dirs.iter().enumerate() {
-        if i < limit {
-            result.push(minify_dir(name));
-        } else {
+        if let Some(icon) = name_icon(name, &icons) {
+            result.push(icon.to_owned());
+        } else if (i == 0 && aliased) || never_abbreviated(name, &whole) {
             result.push(name.to_string());
+        } else if i < limit {
+            let siblings = real_dirs[i].filter(|_| unique && !(i == 0 && name.starts_with('~')))
+                .and_then(siblings);
+            result.push(match siblings {
+                Some(siblings) => unique_prefix(name, &siblings),
+                None => minify_dir(name),
+            });
+        } else {
+            // the names kept whole can still be too long for a prompt
+            result.push(truncate_middle(name, max_length));
+        }
+    }
+    let (mut styles, suffix) = permission_styles(&real_dirs, style, tip);
+    if let Some(last) = styles.last_mut().filter(|_| tip.repo) {
+        *last = repo_style(*last);
+    }
+    let depth = usize::try_from(config::integer("path.max_depth", 0)).unwrap_or(0);
+    elide(&mut result, &mut styles, depth, style);
+    return paint_names(prefix, &result, separator, &styles) + suffix;
+}
+
+pub fn apply_vcs(path: &str, vcs: &dyn git::VCS) -> git::Result<String> {
+    return apply_vcs_styled(path, vcs, 1, Style::fg(Color::BRIGHT_BLUE));
+}
+
+/// The ancestor of `path` that is the repository `root`, as `path` spells
+/// it. Names are compared rather than bytes, so separators and trailing
+/// slashes don't matter, and failing that the directories themselves, for
+/// a root reported through a symlink or in another case.
+fn repo_root<'a>(path: &'a Path, root: &Path) -> Option<&'a Path> {
+    return path.ancestors().find(|ancestor| *ancestor == root)
+        .or_else(|| path.ancestors().find(|ancestor| same_dir(ancestor, root)));
+}
+
+fn apply_vcs_styled(path: &str, vcs: &dyn git::VCS, keep: usize, style: Style) -> git::Result<String> {
+    return apply_vcs_nested(path, &[vcs], 1, keep, style);
+}
+
+/// Like `apply_vcs_styled`, splitting the path at each of the nested
+/// `repos`, outermost first, with each one's status after its root. The
+/// innermost repo has to contain the path; an outer one that doesn't, or
+/// isn't above the repo inside it, is left out. `outer_keep` names are
+/// kept whole up to the outermost root, and `keep` after the innermost.
+fn apply_vcs_nested(path: &str, repos: &[&dyn git::VCS], outer_keep: usize, keep: usize, style: Style) -> git::Result<String> {
+    let (inner, outer) = repos.split_last().ok_or(git::Error::Outside)?;
+    let inner_root = repo_root(Path::new(path), Path::new(&inner.root_dir()?)).ok_or(git::Error::Outside)?;
+    let mut levels = vec![];
+    for vcs in outer {
+        let Ok(reported) = vcs.root_dir() else {
+            continue;
+        };
+        let Some(root) = repo_root(inner_root, Path::new(&reported)) else {
+            continue;
+        };
+        // an ancestor is always a leading slice of the path
+        let end = root.as_os_str().len();
+        if root != inner_root && levels.last().is_none_or(|&(last, _)| last < end) {
+            levels.push((end, *vcs));
+        }
+    }
+    levels.push((inner_root.as_os_str().len(), *inner));
+    // a worktree stands in for everything above it, including the repo
+    // it's usually kept in
+    let mut labels: Vec<Option<String>> = levels.iter().map(|&(end, _)| worktree_label(&path[..end])).collect();
+    let worktree = labels.iter().rposition(Option::is_some).unwrap_or(0);
+    let levels = &levels[worktree..];
+    let label = labels.swap_remove(worktree);
+
+    let remainder = shell_form(&path[levels[levels.len() - 1].0..]);
+    // `path.repo_relative` leaves out where the repo is, showing just its name
+    let relative = config::bool("path.repo_relative", false);
+    let mut result = String::new();
+    let mut start = 0;
+    for (i, &(end, vcs)) in levels.iter().enumerate() {
+        let tip = Tip{cwd: i == levels.len() - 1 && remainder.is_empty(), repo: true};
+        let root = Path::new(&path[..end]);
+        let name = root.file_name().filter(|_| relative).map(|name| name.to_string_lossy());
+        result += &if i > 0 {
+            minify_path(&shell_form(&path[start..end]), 1, style, Some(root), tip)
+        } else if let Some(label) = &label {
+            repo_style(style).paint(label)
+        } else if let Some(name) = name {
+            minify_path(&name, 1, style, Some(root), tip)
+        } else {
+            // only the outermost root is aliased, so an alias inside the repo can't split it
+            minify_path(&alias_with(&tilde(&path[..end]), &aliases()), outer_keep, style, Some(root), tip)
+        };
+        result += &vcs.stat()?;
+        start = end;
+    }
+    let tip = Tip{cwd: !remainder.is_empty(), repo: false};
+    return Ok(result + &minify_path(&remainder, keep, style, Some(Path::new(path)), tip));
+}
+
+/// Options passed on the command line by the shell integration.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Options {
+    /// The zsh keymap, `main` or `vicmd`, passed by the init script in vi mode.
+    pub keymap: Option<String>,
+    /// The shell's history event number, as in `%h` or `\!`.
+    pub history: Option<u64>,
+    /// The exit status of the previous command.
+    pub status: Option<i32>,
+    /// Draw slow segments as placeholders, for the first phase of an async prompt.
+    pub deferred: bool,
+    /// How many trailing directory names are kept whole, overriding
+    /// `path.keep`.
+    pub keep: Option<usize>,
+    /// How many names leading up to the repo root are kept whole,
+    /// overriding `path.outer_keep`.
+    pub outer_keep: Option<usize>,
+    /// How long the previous command ran for.
+    pub duration: Option<Duration>,
+    /// The number of jobs the shell has in the background.
+    pub jobs: Option<usize>,
+    /// How the statusline is encoded.
+    pub format: style::Format,
+    /// The directory to draw the statusline for, instead of the cwd.
+    pub path: Option<PathBuf>,
+}
+
+/// The minified working directory, keeping the last `keep` names whole,
+/// with the VCS status spliced in at the repo root when `vcs` is set. In a
+/// repo, `keep` applies to the names below its root and `outer_keep` to
+/// those up to and including it.
+pub struct PathSegment {
+    pub keep: usize,
+    pub outer_keep: usize,
+    pub vcs: bool,
+    /// How the directory names are drawn.
+    pub style: Style,
+}
+
+impl Default for PathSegment {
+    fn default() -> PathSegment {
+        return PathSegment{keep: 1, outer_keep: 1, vcs: true, style: Style::fg(Color::BRIGHT_BLUE)};
+    }
+}
+
+impl Segment for PathSegment {
+    fn name(&self) -> &str {
+        return "path";
+    }
+
+    fn render(&self, ctx: &segments::Context) -> Option<StyledText> {
+        // a name that isn't UTF-8 is shown with replacement characters, as
+        // git's output is, so the repo root still lines up with the path
+        let path = ctx.path.to_string_lossy();
+        if !self.vcs {
+            let text = StyledText::from_ansi(&minify_path(&named_path(&path), self.keep, self.style, Some(&ctx.path), Tip::CWD));
+            return Some(apply_gradient(text, self.style, &gradient_colors(self.style)));
         }
+        // outside a repo, or without git, there's still the path
+        let text = ctx.git()
+            .and_then(|summary| {
+                let mut repos: Vec<&dyn git::VCS> = ctx.enclosing_git().iter().map(|outer| outer as &dyn git::VCS).collect();
+                repos.push(summary);
+                return apply_vcs_nested(&path, &repos, self.outer_keep, self.keep, self.style).ok();
+            })
+            .unwrap_or_else(|| minify_path(&named_path(&path), self.keep, self.style, Some(&ctx.path), Tip::CWD));
+        return Some(apply_gradient(StyledText::from_ansi(&text), self.style, &gradient_colors(self.style)));
+    }
+}
+
+/// The VCS status on its own, for when `segments.order` places it apart
+/// from the path rather than spliced in at the repo root.
+pub struct GitSegment;
+
+impl Segment for GitSegment {
+    fn name(&self) -> &str {
+        return "git";
+    }
+
+    /// Apart from the path, the branch is always shown.
+    fn render(&self, ctx: &segments::Context) -> Option<StyledText> {
+        return Some(StyledText::from_ansi(&ctx.git()?.decoration(true)));
     }
-    return "\x1b[94m".to_owned() + &result.join("/") + "\x1b[m";
 }
 
-pub fn apply_vcs(path: &str, vcs: &dyn git::VCS) -> String {
-    let root = vcs.root_dir();
-    let common = &path[0..root.len()];
-    let remainder = &path[root.len()..];
-    return minify_path(&common, 1) + &vcs.stat() + &minify_path(&remainder, 1);
+/// The path followed by every builtin segment.
+pub fn registry() -> Registry {
+    let mut registry = Registry::default();
+    registry.register(PathSegment::default());
+    registry.extend(Registry::builtin());
+    return registry;
+}
+
+/// The statusline for the cwd, or `--path`. This never panics: if
+/// rendering fails the raw path is printed instead, so the prompt is never
+/// left empty.
+pub fn statusline(options: &Options) -> String {
+    return statusline_timed(options).0;
+}
+
+/// The VCS status of the cwd's repository on its own, as `statusline git`
+/// prints it for other prompts, tmux or scripts to embed. None outside a
+/// repository.
+pub fn git_status(options: &Options) -> Option<String> {
+    let ctx = segments::Context::new(target(options), options.clone());
+    let status = builder::Statusline::builder().with(GitSegment).format(options.format).build().render(&ctx);
+    return Some(status).filter(|status| !status.is_empty());
+}
+
+/// The state of the cwd's repository as data, as `statusline git --json`
+/// prints it.
+pub fn git_summary(options: &Options) -> Option<git::Summary> {
+    return git::summary_at(&target(options));
+}
+
+/// The state of the repository, for `statusline --quiet` to exit with.
+/// Scripts act on it, so it's gathered afresh rather than from the cache.
+pub fn repo_state(options: &Options) -> git::State {
+    return git::Git::new(&target(options)).summary().map_or(git::State::Outside, |summary| summary.state());
+}
+
+/// The minified cwd on its own, without the VCS status, as `statusline
+/// path` prints it for prompts drawing git some other way. The names are
+/// drawn in `color`, a name such as `cyan` or `214`, if given.
+pub fn minified_path(options: &Options, color: Option<&str>) -> String {
+    let ctx = segments::Context::new(target(options), options.clone());
+    let mut builder = builder::Statusline::builder().path(keep(options));
+    if let Some(color) = color {
+        builder = builder.path_color(color);
+    }
+    return builder.format(options.format).build().render(&ctx);
 }
 
-pub fn statusline() -> String {
-    if let Some(path) = env::current_dir().unwrap().to_str() {
-        return apply_vcs(&path, &git::Git{});
+/// Write the bare minified path at once, then redraw the line as the whole
+/// statusline when git and the other segments are done, for wrappers that
+/// draw a prompt as it arrives. The prompt can be used from the start
+/// however slow the repository is. Only ANSI output can be redrawn, so in
+/// other formats the whole statusline is written once it's done, and with
+/// a `shell` both writes have their escapes marked as taking no room.
+pub fn stream(options: &Options, shell: Option<style::Shell>, out: &mut impl io::Write) -> io::Result<()> {
+    let wrap = |text: &str| shell.map_or_else(|| text.to_owned(), |shell| shell.wrap(text));
+    if options.format != style::Format::Ansi {
+        return writeln!(out, "{}", wrap(&statusline(options)));
     }
-    return "".to_owned();
+    let path = target(options);
+    let early = panic::catch_unwind(|| {
+        let ctx = segments::Context::new(path.clone(), options.clone());
+        let segment = PathSegment{vcs: false, ..PathSegment::default()};
+        return segment.render(&ctx).map(|text| text.render(options.format));
+    });
+    let early = early.ok().flatten().unwrap_or_else(|| path.to_string_lossy().into_owned());
+    write!(out, "{}", wrap(&early))?;
+    out.flush()?;
+    // back to the first column with an escape rather than `\r`, so that a
+    // shell is told it takes no room
+    return writeln!(out, "{}", wrap(&format!("\x1b[1G\x1b[K{}", statusline(options))));
+}
+
+#[cfg(unix)]
+fn same_dir(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    return match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    };
+}
+
+#[cfg(not(unix))]
+fn same_dir(a: &Path, b: &Path) -> bool {
+    return fs::canonicalize(a).is_ok_and(|a| fs::canonicalize(b).is_ok_and(|b| a == b));
+}
+
+/// The directory the prompt is for. The shell's `$PWD` keeps the symlinks
+/// it was reached through, so it's used while it's still the same
+/// directory, unless `path.physical` asks for the resolved path.
+pub fn cwd() -> PathBuf {
+    let physical = env::current_dir().ok();
+    let logical = env::var_os("PWD").map(PathBuf::from).filter(|pwd| pwd.is_absolute());
+    return match (physical, logical) {
+        (Some(physical), Some(logical)) if !config::bool("path.physical", false) && same_dir(&physical, &logical) => logical,
+        (Some(physical), _) => physical,
+        // a deleted cwd can't be resolved any more, but the shell still knows it
+        (None, Some(logical)) => logical,
+        (None, None) => PathBuf::from("?"),
+    };
+}
+
+/// The directory the statusline is drawn for: `--path` resolved, when
+/// it's given, else the cwd. A path that doesn't exist is still drawn, as a
+/// deleted cwd is.
+pub fn target(options: &Options) -> PathBuf {
+    let Some(path) = &options.path else {
+        return cwd();
+    };
+    return fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.clone());
+}
+
+/// Like `statusline`, also returning how long each segment took.
+pub fn statusline_timed(options: &Options) -> (String, Vec<Timing>) {
+    let path = target(options);
+    let fallback = path.to_string_lossy().into_owned();
+    return panic::catch_unwind(|| render_timed(path, options)).unwrap_or((fallback, vec![]));
+}
+
+/// The statusline for `path`, as `statusline` draws it for the cwd.
+pub fn render(path: PathBuf, options: &Options) -> String {
+    return render_timed(path, options).0;
+}
+
+/// Everything `statusline` would show for the cwd, as data.
+pub fn report(options: &Options) -> StatusReport {
+    return report_timed(options).0;
+}
+
+/// Like `report`, also returning how long each segment took and why it
+/// was shown or hidden.
+pub fn report_timed(options: &Options) -> (StatusReport, Vec<Timing>) {
+    let ctx = segments::Context::new(target(options), options.clone());
+    return statusline_builder(options).collect(&ctx);
+}
+
+/// The names kept whole after the repo root, or in the whole path outside
+/// one: `--keep`, else `path.keep`, else 1.
+fn keep(options: &Options) -> usize {
+    return options.keep.unwrap_or_else(|| config::integer("path.keep", 1).try_into().unwrap_or(1));
+}
+
+/// The names kept whole up to the repo root: `--outer-keep`, else
+/// `path.outer_keep`, else 1.
+fn outer_keep(options: &Options) -> usize {
+    return options.outer_keep.unwrap_or_else(|| config::integer("path.outer_keep", 1).try_into().unwrap_or(1));
+}
+
+fn statusline_builder(options: &Options) -> builder::Statusline {
+    return builder::Statusline::builder()
+        .path(keep(options))
+        .outer_keep(outer_keep(options))
+        .git()
+        .builtins()
+        .plugins()
+        .format(options.format)
+        .build();
+}
+
+fn render_timed(path: PathBuf, options: &Options) -> (String, Vec<Timing>) {
+    let ctx = segments::Context::new(path, options.clone());
+    return statusline_builder(options).render_timed(&ctx);
 }
 
 #[cfg(test)]
@@ -54,18 +837,181 @@ mod tests {
     #[case("~root", "~r")]
     #[case("private_dot_config", "p")]
     #[case("._shares", "._")]
+    #[case("\u{65e5}\u{672c}\u{8a9e}", "\u{65e5}")]
+    #[case("e\u{301}migre\u{301}", "e\u{301}")]
+    #[case("\u{1F680}rocket", "\u{1F680}r")]
+    #[case("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}", "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}")]
     fn test_minify_dir(#[case] input: &str, #[case] expected: &str) {
         let actual = minify_dir(input);
         assert_eq!(expected, actual)
     }
 
+    #[rstest]
+    #[case("/home/kevna", Some("/home/kevna"), "~")]
+    #[case("/home/kevna/src/", Some("/home/kevna/"), "~/src/")]
+    #[case("/home/kevnas", Some("/home/kevna"), "/home/kevnas")]
+    #[case("/home/alice/src", Some("/home/kevna"), "~alice/src")]
+    #[case("/root", None, "~root")]
+    #[case("/bin", Some("/"), "/bin")]
+    #[case("/srv/alice", Some("/home/kevna"), "/srv/alice")]
+    fn test_tilde(#[case] path: &str, #[case] home: Option<&str>, #[case] expected: &str) {
+        let homes = |name: &str| match name {
+            "alice" => Some("/home/alice".to_owned()),
+            "root" => Some("/root".to_owned()),
+            _ => None,
+        };
+        let actual = tilde_with(path, home, homes);
+        assert_eq!(expected, actual)
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    #[case("# Min/max values for automatic uid selection\nUID_MIN\t\t\t 1000\nUID_MAX\t\t\t60000\n", Some(1000))]
+    #[case("#UID_MIN 500\nSYS_UID_MAX 999\n", None)]
+    #[case("UID_MIN 500\n", Some(500))]
+    fn test_parse_uid_min(#[case] defs: &str, #[case] expected: Option<u32>) {
+        let actual = parse_uid_min(defs);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("node_modules", Some("\u{E718}"))]
+    #[case("node", None)]
+    #[case("tmp", None)]
+    fn test_name_icon(#[case] name: &str, #[case] expected: Option<&str>) {
+        let icons: Vec<(String, String)> = DEFAULT_ICONS.iter().map(|&(dir, icon)| (dir.to_owned(), icon.to_owned())).collect();
+        let actual = name_icon(name, &icons);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("~/src/company/api", "~work/api")]
+    #[case("~/src/company/infra/k8s", "~k8s")]
+    #[case("~/src/companyx", "~/src/companyx")]
+    fn test_alias_with(#[case] path: &str, #[case] expected: &str) {
+        let aliases = vec![
+            ("~k8s".to_owned(), "~/src/company/infra/k8s".to_owned()),
+            ("~work".to_owned(), "~/src/company".to_owned()),
+        ];
+        let actual = alias_with(path, &aliases);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("private_dot_config", 3, false, "pri")]
+    #[case(".config", 2, false, ".co")]
+    #[case(".config", 2, true, ".c")]
+    #[case(".config", 1, true, ".c")]
+    #[case("foo-bar", 5, false, "foo-b")]
+    #[case("ab", 3, false, "ab")]
+    fn test_minify_dir_to(#[case] input: &str, #[case] length: usize, #[case] count_dots: bool, #[case] expected: &str) {
+        let actual = minify_dir_to(input, length, count_dots);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("customer-data-export-pipeline-v2-backup-2024", 20, "customer-d\u{2026}ckup-2024")]
+    #[case("short", 20, "short")]
+    #[case("abcdef", 0, "abcdef")]
+    #[case("\u{65e5}\u{672c}\u{8a9e}\u{65e5}\u{672c}", 4, "\u{65e5}\u{672c}\u{2026}\u{672c}")]
+    fn test_truncate_middle(#[case] name: &str, #[case] max: usize, #[case] expected: &str) {
+        let actual = truncate_middle(name, max);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("src", true)]
+    #[case("billing-prod", true)]
+    #[case("billing-staging", false)]
+    #[case("srcs", false)]
+    fn test_never_abbreviated(#[case] name: &str, #[case] expected: bool) {
+        let actual = never_abbreviated(name, &["src", "work", "*-prod"]);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("documents", &["downloads", "desktop"], "doc")]
+    #[case("desktop", &["documents", "downloads"], "de")]
+    #[case("docs", &["docs-old"], "docs")]
+    #[case("src", &["bin", "src"], "s")]
+    #[case(".config", &[".cache"], ".co")]
+    fn test_unique_prefix(#[case] name: &str, #[case] siblings: &[&str], #[case] expected: &str) {
+        let siblings: Vec<String> = siblings.iter().map(|sibling| sibling.to_string()).collect();
+        let actual = unique_prefix(name, &siblings);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case(r"C:\Users\kevna", ("C:", r"\Users\kevna"))]
+    #[case(r"\\?\D:\data", ("D:", r"\data"))]
+    #[case(r"\\fileserver\team\docs", (r"\\fileserver\team", r"\docs"))]
+    #[case(r"\\?\UNC\fileserver\team\docs", (r"\\fileserver\team", r"\docs"))]
+    #[case("/home/kevna", ("", "/home/kevna"))]
+    fn test_split_prefix(#[case] path: &str, #[case] expected: (&str, &str)) {
+        let (prefix, rest) = split_prefix(path);
+        assert_eq!(expected, (prefix.as_str(), rest))
+    }
+
+    #[rstest]
+    #[case(r"C:\Users\kevna", "/c/Users/kevna")]
+    #[case(r"\\fileserver\team\docs", "//fileserver/team/docs")]
+    #[case(r"~\src", "~/src")]
+    fn test_msys_path(#[case] path: &str, #[case] expected: &str) {
+        let actual = msys_path(path);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case(r"C:\Users\kevna\src\project", r"C:\U\k\s\project")]
+    #[case(r"\\?\C:\Windows\System32", r"C:\W\System32")]
+    #[case(r"\\fileserver\team\reports\2024", r"\\fileserver\team\r\2024")]
+    #[case(r"~\Documents\notes", r"~\D\notes")]
+    #[case("/srv/back\\slash/x", "/s/b/x")]
+    fn test_minify_windows_path(#[case] input: &str, #[case] expected: &str) {
+        let actual = minify_path(input, 1, Style::default(), None, Tip::CWD);
+        assert_eq!(expected, actual)
+    }
+
     #[rstest]
     #[case("~", 1, "\x1b[94m~\x1b[m")]
     #[case("/etc/X11/xorg.conf.d", 1, "\x1b[94m/e/X/xorg.conf.d\x1b[m")]
     #[case("~/.local/share/chezmoi/private_dot_config/i3", 1, "\x1b[94m~/.l/s/c/p/i3\x1b[m")]
     #[case("~/.local/share/chezmoi/private_dot_config/i3", 2, "\x1b[94m~/.l/s/c/private_dot_config/i3\x1b[m")]
     fn test_minify_path(#[case] input: &str, #[case] keep: usize, #[case] expected: &str) {
-        let actual = minify_path(input, keep);
+        let actual = minify_path(input, keep, Style::fg(Color::BRIGHT_BLUE), None, Tip::CWD);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case(&["~", "a", "b", "c", "project", "src"], 3, &["~", "\u{2026}3\u{2026}", "project", "src"])]
+    #[case(&["~", "a", "project", "src"], 3, &["~", "a", "project", "src"])]
+    #[case(&["", "a", "b", "src"], 2, &["", "\u{2026}2\u{2026}", "src"])]
+    #[case(&["~", "a", "b", "c", "src"], 0, &["~", "a", "b", "c", "src"])]
+    fn test_elide(#[case] names: &[&str], #[case] depth: usize, #[case] expected: &[&str]) {
+        let mut names: Vec<String> = names.iter().map(|&name| name.to_owned()).collect();
+        let mut styles = vec![Style::default(); names.len()];
+        elide(&mut names, &mut styles, depth, Style::default());
+        assert_eq!(expected, names);
+        assert_eq!(names.len(), styles.len())
+    }
+
+    #[rstest]
+    #[case(&[Style::fg(Color::BRIGHT_BLUE); 3], "\x1b[94m/s/src\x1b[m")]
+    #[case(&[Style::fg(Color::BRIGHT_BLUE), Style::fg(Color::BRIGHT_BLUE), Style::fg(Color::RED)], "\x1b[94m/s/\x1b[m\x1b[31msrc\x1b[m")]
+    #[case(&[Style::fg(Color::BRIGHT_BLUE), Style::fg(Color::GRAY), Style::fg(Color::BRIGHT_BLUE)], "\x1b[94m/\x1b[m\x1b[90ms/\x1b[m\x1b[94msrc\x1b[m")]
+    fn test_paint_names(#[case] styles: &[Style], #[case] expected: &str) {
+        let names = ["".to_owned(), "s".to_owned(), "src".to_owned()];
+        let actual = paint_names(String::new(), &names, "/", styles);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case(&[Color::Rgb(0, 0, 0), Color::Rgb(200, 100, 0)], "\x1b[38;2;0;0;0m/\x1b[38;2;100;50;0ms/\x1b[38;2;200;100;0msrc\x1b[m")]
+    #[case(&[Color::RED], "\x1b[31m/s/src\x1b[m")]
+    #[case(&[], "\x1b[94m/s/src\x1b[m")]
+    fn test_apply_gradient(#[case] colors: &[Color], #[case] expected: &str) {
+        let text = StyledText::new().push(Style::fg(Color::BRIGHT_BLUE), "/s/src");
+        let actual = apply_gradient(text, Style::fg(Color::BRIGHT_BLUE), colors).to_ansi();
         assert_eq!(expected, actual)
     }
 
@@ -76,16 +1022,16 @@ mod tests {
     }
 
     impl git::VCS for MockVCS {
-        fn root_dir(&self) -> String {
-            return self.root.to_owned();
+        fn root_dir(&self) -> git::Result<String> {
+            return Ok(self.root.to_owned());
         }
 
-        fn branch(&self) -> String {
-            return self.branch.to_owned();
+        fn branch(&self) -> git::Result<String> {
+            return Ok(self.branch.to_owned());
         }
 
-        fn stat(&self) -> String {
-            return self.stat.to_owned();
+        fn stat(&self) -> git::Result<String> {
+            return Ok(self.stat.to_owned());
         }
     }
 
@@ -95,7 +1041,7 @@ mod tests {
         "branch",
         "\u{E0A0}master",
         "~/.local/share/chezmoi/private_dot_config/i3",
-        "\x1b[94m~/.l/s/chezmoi\x1b[m\u{E0A0}master\x1b[94m/p/i3\x1b[m",
+        "\x1b[94m~/.l/s/\x1b[m\x1b[1;94mchezmoi\x1b[m\u{E0A0}master\x1b[94m/p/i3\x1b[m",
     )]
     #[case(
         "~/Documents/python/statusline/master",
@@ -103,21 +1049,21 @@ mod tests {
         "\u{E0A0}",
         "~/Documents/python/statusline/master/statusline",
         // "\x1b[94m~/D/p/statusline/master\x1b[m\u{E0A0}\x1b[94m/statusline\x1b[m",
-        "\x1b[94m~/D/p/s/master\x1b[m\u{E0A0}\x1b[94m/statusline\x1b[m",
+        "\x1b[94m~/D/p/s/\x1b[m\x1b[1;94mmaster\x1b[m\u{E0A0}\x1b[94m/statusline\x1b[m",
     )]
     #[case(
         "~/Documents/python/statusline-master",
         "branch",
         "\u{E0A0}",
         "~/Documents/python/statusline-master/statusline",
-        "\x1b[94m~/D/p/statusline-master\x1b[m\u{E0A0}\x1b[94m/statusline\x1b[m",
+        "\x1b[94m~/D/p/\x1b[m\x1b[1;94mstatusline-master\x1b[m\u{E0A0}\x1b[94m/statusline\x1b[m",
     )]
     #[case(
         "~/Documents/python/statusline/feature/newfeature",
         "feature/newfeature",
         "\u{E0A0}",
         "~/Documents/python/statusline/feature/newfeature/statusline",
-        "\x1b[94m~/D/p/s/f/newfeature\x1b[m\u{E0A0}\x1b[94m/statusline\x1b[m",
+        "\x1b[94m~/D/p/s/f/\x1b[m\x1b[1;94mnewfeature\x1b[m\u{E0A0}\x1b[94m/statusline\x1b[m",
     )]
     fn test_apply_vcs(#[case] root: &str, #[case] branch: &str, #[case] stat: &str, #[case] input: &str, #[case] expected: &str) {
         let mock = MockVCS{
@@ -125,7 +1071,87 @@ mod tests {
             branch: branch.to_owned(),
             stat: stat.to_owned(),
         };
-        let actual = apply_vcs(input, &mock);
+        let actual = apply_vcs(input, &mock).unwrap();
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("/srv/repo/", "/srv/repo/src", "\x1b[94m/s/\x1b[m\x1b[1;94mrepo\x1b[m\u{E0A0}\x1b[94m/src\x1b[m")]
+    #[case("/srv//repo", "/srv/repo/src", "\x1b[94m/s/\x1b[m\x1b[1;94mrepo\x1b[m\u{E0A0}\x1b[94m/src\x1b[m")]
+    #[case("/srv/repo", "/srv/repository/src", "\x1b[94m/s/r/src\x1b[m")]
+    #[case("/srv/\u{65e5}\u{672c}", "/srv/\u{65e5}\u{672c}/src", "\x1b[94m/s/\x1b[m\x1b[1;94m\u{65e5}\u{672c}\x1b[m\u{E0A0}\x1b[94m/src\x1b[m")]
+    fn test_apply_vcs_root_spelling(#[case] root: &str, #[case] input: &str, #[case] expected: &str) {
+        let mock = MockVCS{
+            root: root.to_owned(),
+            branch: "main".to_owned(),
+            stat: "\u{E0A0}".to_owned(),
+        };
+        let actual = apply_vcs(input, &mock).unwrap_or_else(|_| minify_path(input, 1, Style::fg(Color::BRIGHT_BLUE), None, Tip::CWD));
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("/srv/app/vendor/lib/src", 1, "\x1b[94m/s/\x1b[m\x1b[1;94mapp\x1b[m\u{E0A0}main\x1b[94m/v/\x1b[m\x1b[1;94mlib\x1b[m\u{E0A0}v2\x1b[94m/src\x1b[m")]
+    #[case("/srv/app/vendor/lib", 1, "\x1b[94m/s/\x1b[m\x1b[1;94mapp\x1b[m\u{E0A0}main\x1b[94m/v/\x1b[m\x1b[1;94mlib\x1b[m\u{E0A0}v2\x1b[94m\x1b[m")]
+    #[case("/srv/app/vendor/lib/src", 2, "\x1b[94m/srv/\x1b[m\x1b[1;94mapp\x1b[m\u{E0A0}main\x1b[94m/v/\x1b[m\x1b[1;94mlib\x1b[m\u{E0A0}v2\x1b[94m/src\x1b[m")]
+    fn test_apply_vcs_nested(#[case] input: &str, #[case] outer_keep: usize, #[case] expected: &str) {
+        let outer = MockVCS{root: "/srv/app".to_owned(), branch: "main".to_owned(), stat: "\u{E0A0}main".to_owned()};
+        let inner = MockVCS{root: "/srv/app/vendor/lib".to_owned(), branch: "v2".to_owned(), stat: "\u{E0A0}v2".to_owned()};
+        let actual = apply_vcs_nested(input, &[&outer, &inner], outer_keep, 1, Style::fg(Color::BRIGHT_BLUE)).unwrap();
         assert_eq!(expected, actual)
     }
+
+    #[test]
+    fn test_apply_vcs_outside_root() {
+        let mock = MockVCS{
+            root: "/srv/repo".to_owned(),
+            branch: "main".to_owned(),
+            stat: "\u{E0A0}".to_owned(),
+        };
+        let actual = apply_vcs("/home/user", &mock);
+        assert!(matches!(actual, Err(git::Error::Outside)))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"/srv/\xffdata/src"));
+        let ctx = segments::Context::new(path.to_path_buf(), Options::default());
+        let segment = PathSegment{vcs: false, ..PathSegment::default()};
+        let actual = segment.render(&ctx).map(|text| text.render(style::Format::Ansi));
+        assert_eq!(Some("\x1b[94m/s/\u{FFFD}d/src\x1b[m".to_owned()), actual)
+    }
+
+    #[rstest]
+    #[case(style::Format::Plain)]
+    #[case(style::Format::Tmux)]
+    #[case(style::Format::Html)]
+    fn test_stream_not_ansi(#[case] format: style::Format) {
+        let options = Options{path: Some(PathBuf::from("/nonexistent/srv")), format, ..Options::default()};
+        let mut out = vec![];
+        stream(&options, None, &mut out).unwrap();
+        let actual = String::from_utf8(out).unwrap();
+        assert!(!actual.contains('\x1b') && actual.lines().count() == 1, "{actual:?}")
+    }
+
+    #[test]
+    fn test_stream_shell() {
+        let options = Options{path: Some(PathBuf::from("/nonexistent/srv")), ..Options::default()};
+        let mut out = vec![];
+        stream(&options, Some(style::Shell::Bash), &mut out).unwrap();
+        let actual = String::from_utf8(out).unwrap();
+        assert!(actual.contains("\x01\x1b[1G\x02\x01\x1b[K\x02"), "{actual:?}");
+        assert_eq!(actual.matches('\x1b').count(), actual.matches("\x01\x1b").count())
+    }
+
+    #[cfg(unix)]
+    #[rstest]
+    #[case("/nonexistent/srv/../repo", "/nonexistent/srv/../repo")]
+    #[case("/", "/")]
+    fn test_target(#[case] path: &str, #[case] expected: &str) {
+        let options = Options{path: Some(PathBuf::from(path)), ..Options::default()};
+        let actual = target(&options);
+        assert_eq!(PathBuf::from(expected), actual)
+    }
 }
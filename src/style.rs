@@ -0,0 +1,505 @@
+use std::fmt::{self, Write};
+use serde::{Deserialize, Serialize};
+
+/// Resets all attributes back to the terminal's defaults.
+pub const RESET: &str = "\x1b[m";
+
+const NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+/// A terminal color.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Color {
+    /// One of the 16 theme colors, 8-15 being the bright variants.
+    Ansi(u8),
+    /// An entry of the 256-color palette.
+    Fixed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    pub const RED: Color = Color::Ansi(1);
+    pub const GREEN: Color = Color::Ansi(2);
+    pub const YELLOW: Color = Color::Ansi(3);
+    pub const CYAN: Color = Color::Ansi(6);
+    pub const GRAY: Color = Color::Ansi(8);
+    pub const BRIGHT_BLUE: Color = Color::Ansi(12);
+
+    /// A color name (`red`, `bright-blue`), 256-color number or `#rrggbb`.
+    pub fn parse(name: &str) -> Option<Color> {
+        if let Ok(number) = name.parse::<u8>() {
+            return Some(Color::Fixed(number));
+        }
+        if let Some(hex) = name.strip_prefix('#').filter(|hex| hex.len() == 6) {
+            let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+            return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+        let (base, bright) = match name.strip_prefix("bright-") {
+            Some(base) => (base, 8),
+            None => (name, 0),
+        };
+        let index = NAMES.iter().position(|&color| color == base)? as u8;
+        return Some(Color::Ansi(index + bright));
+    }
+
+    fn sgr(self) -> String {
+        return match self {
+            Color::Ansi(n) if n < 8 => format!("{}", 30 + n),
+            Color::Ansi(n) => format!("{}", 90 + (n & 7)),
+            Color::Fixed(n) => format!("38;5;{n}"),
+            Color::Rgb(r, g, b) => format!("38;2;{r};{g};{b}"),
+        };
+    }
+
+    /// The same color as a background.
+    fn bg_sgr(self) -> String {
+        return match self {
+            Color::Ansi(n) if n < 8 => format!("{}", 40 + n),
+            Color::Ansi(n) => format!("{}", 100 + (n & 7)),
+            Color::Fixed(n) => format!("48;5;{n}"),
+            Color::Rgb(r, g, b) => format!("48;2;{r};{g};{b}"),
+        };
+    }
+
+    fn css(self) -> String {
+        let (r, g, b) = self.rgb();
+        return format!("#{r:02x}{g:02x}{b:02x}");
+    }
+
+    /// Approximate RGB values, for formats without a palette.
+    pub fn rgb(self) -> (u8, u8, u8) {
+        const BASE: [(u8, u8, u8); 16] = [
+            (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0),
+            (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+            (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+            (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+        ];
+        return match self {
+            Color::Ansi(n) | Color::Fixed(n @ 0..=15) => BASE[(n & 15) as usize],
+            Color::Fixed(n @ 16..=231) => {
+                let level = |i: u8| if i == 0 { 0 } else { 55 + i * 40 };
+                let n = n - 16;
+                (level(n / 36), level(n / 6 % 6), level(n % 6))
+            },
+            Color::Fixed(n) => {
+                let gray = 8 + (n - 232) * 10;
+                (gray, gray, gray)
+            },
+            Color::Rgb(r, g, b) => (r, g, b),
+        };
+    }
+
+    /// The color `t` of the way from this one to `other`, 0 to 1.
+    pub fn mix(self, other: Color, t: f64) -> Color {
+        let ((r1, g1, b1), (r2, g2, b2)) = (self.rgb(), other.rgb());
+        let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t.clamp(0.0, 1.0)).round() as u8;
+        return Color::Rgb(channel(r1, r2), channel(g1, g2), channel(b1, b2));
+    }
+
+    fn tmux(self) -> String {
+        return match self {
+            Color::Ansi(n) | Color::Fixed(n) => format!("colour{n}"),
+            Color::Rgb(r, g, b) => format!("#{r:02x}{g:02x}{b:02x}"),
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Style {
+    pub fg: Option<Color>,
+    #[serde(default)]
+    pub bg: Option<Color>,
+    pub bold: bool,
+}
+
+impl Style {
+    pub fn fg(color: Color) -> Style {
+        return Style{fg: Some(color), ..Style::default()};
+    }
+
+    pub fn on(self, color: Color) -> Style {
+        return Style{bg: Some(color), ..self};
+    }
+
+    pub fn bold(self) -> Style {
+        return Style{bold: true, ..self};
+    }
+
+    pub fn is_plain(&self) -> bool {
+        return *self == Style::default();
+    }
+
+    /// The escape sequence switching to this style.
+    pub fn prefix(&self) -> String {
+        let mut codes = vec![];
+        if self.bold {
+            codes.push("1".to_owned());
+        }
+        codes.extend(self.fg.map(Color::sgr));
+        codes.extend(self.bg.map(Color::bg_sgr));
+        return format!("\x1b[{}m", codes.join(";"));
+    }
+
+    /// `text` in this style, as ANSI, resetting afterwards.
+    pub fn paint(&self, text: &str) -> String {
+        if self.is_plain() {
+            return text.to_owned();
+        }
+        return format!("{}{text}{RESET}", self.prefix());
+    }
+}
+
+/// The name `Color::parse` reads back.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match *self {
+            Color::Ansi(n) if n < 8 => write!(f, "{}", NAMES[n as usize]),
+            Color::Ansi(n) => write!(f, "bright-{}", NAMES[(n & 7) as usize]),
+            Color::Fixed(n) => write!(f, "{n}"),
+            Color::Rgb(r, g, b) => write!(f, "#{r:02x}{g:02x}{b:02x}"),
+        };
+    }
+}
+
+/// E.g. `bold red on white`, or `default` for no styling.
+impl fmt::Display for Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut words = vec![];
+        if self.bold {
+            words.push("bold".to_owned());
+        }
+        words.extend(self.fg.map(|color| color.to_string()));
+        words.extend(self.bg.map(|color| format!("on {color}")));
+        if words.is_empty() {
+            return write!(f, "default");
+        }
+        return write!(f, "{}", words.join(" "));
+    }
+}
+
+/// How rendered text is encoded.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// ANSI escape sequences, for terminals and shell prompts.
+    #[default]
+    Ansi,
+    /// tmux `#[fg=...]` style directives, for status-left/right.
+    Tmux,
+    /// Text only, for bars and editors that do their own styling.
+    Plain,
+    /// `<span>`s with inline CSS.
+    Html,
+}
+
+/// The shell a prompt is printed for, which has to be told which bytes of
+/// it take up no columns to keep the cursor in the right place.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Powershell,
+}
+
+impl Shell {
+    /// Mark each escape sequence in `ansi` as zero-width the way the shell
+    /// expects, and for zsh, keep a `%` in the text from being read as a
+    /// prompt escape of its own.
+    pub fn wrap(self, ansi: &str) -> String {
+        let (start, end) = match self {
+            Shell::Bash => ("\x01", "\x02"),
+            Shell::Zsh => ("%{", "%}"),
+            // PSReadLine measures escape sequences itself
+            Shell::Powershell => return ansi.to_owned(),
+        };
+        let mut result = String::new();
+        let mut chars = ansi.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1b' => {
+                    result += start;
+                    result.push(c);
+                    let next = chars.next();
+                    result.extend(next);
+                    // a CSI sequence runs up to a byte in `@`..`~`
+                    if next == Some('[') {
+                        for c in chars.by_ref() {
+                            result.push(c);
+                            if ('@'..='~').contains(&c) {
+                                break;
+                            }
+                        }
+                    }
+                    result += end;
+                },
+                '%' if self == Shell::Zsh => result += "%%",
+                _ => result.push(c),
+            }
+        }
+        return result;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub style: Style,
+    pub text: String,
+}
+
+/// Segment output as styled spans, serialized per output format.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StyledText {
+    pub spans: Vec<Span>,
+}
+
+impl StyledText {
+    pub fn new() -> StyledText {
+        return StyledText::default();
+    }
+
+    /// Append `text` in `style`.
+    pub fn push(mut self, style: Style, text: &str) -> StyledText {
+        if text.is_empty() {
+            return self;
+        }
+        if let Some(last) = self.spans.last_mut().filter(|last| last.style == style) {
+            last.text += text;
+            return self;
+        }
+        self.spans.push(Span{style, text: text.to_owned()});
+        return self;
+    }
+
+    pub fn append(mut self, other: StyledText) -> StyledText {
+        for span in other.spans {
+            self = self.push(span.style, &span.text);
+        }
+        return self;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.spans.is_empty();
+    }
+
+    /// Parse text with SGR escape sequences, as segments print them. Other
+    /// escapes are kept as text.
+    pub fn from_ansi(text: &str) -> StyledText {
+        let mut result = StyledText::new();
+        let mut style = Style::default();
+        let mut rest = text;
+        while let Some(start) = rest.find("\x1b[") {
+            let (before, escape) = rest.split_at(start);
+            result = result.push(style, before);
+            let body = &escape[2..];
+            let Some(end) = body.find(|c: char| !c.is_ascii_digit() && c != ';') else {
+                result = result.push(style, escape);
+                rest = "";
+                break;
+            };
+            if !body[end..].starts_with('m') {
+                let terminator = body[end..].chars().next().map_or(0, char::len_utf8);
+                result = result.push(style, &escape[..2 + end + terminator]);
+                rest = &body[end + terminator..];
+                continue;
+            }
+            style = apply_sgr(style, &body[..end]);
+            rest = &body[end + 1..];
+        }
+        return result.push(style, rest);
+    }
+
+    pub fn render(&self, format: Format) -> String {
+        return match format {
+            Format::Ansi => self.to_ansi(),
+            Format::Plain => self.spans.iter().map(|span| span.text.as_str()).collect(),
+            Format::Tmux => self.spans.iter().map(|span| {
+                let text = span.text.replace('#', "##");
+                let mut directives = vec![];
+                directives.extend(span.style.fg.map(|fg| format!("fg={}", fg.tmux())));
+                directives.extend(span.style.bg.map(|bg| format!("bg={}", bg.tmux())));
+                if span.style.bold {
+                    directives.push("bold".to_owned());
+                }
+                if directives.is_empty() {
+                    return text;
+                }
+                format!("#[{}]{text}#[default]", directives.join(","))
+            }).collect(),
+            Format::Html => self.spans.iter().map(|span| {
+                let text = span.text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+                if span.style.is_plain() {
+                    return text;
+                }
+                let mut css = String::new();
+                if let Some(fg) = span.style.fg {
+                    let _ = write!(css, "color:{};", fg.css());
+                }
+                if let Some(bg) = span.style.bg {
+                    let _ = write!(css, "background-color:{};", bg.css());
+                }
+                if span.style.bold {
+                    css += "font-weight:bold;";
+                }
+                format!("<span style=\"{css}\">{text}</span>")
+            }).collect(),
+        };
+    }
+
+    /// ANSI, only emitting escapes where the style changes.
+    pub fn to_ansi(&self) -> String {
+        let mut result = String::new();
+        let mut current = Style::default();
+        for span in &self.spans {
+            if span.style != current {
+                // attributes can only be turned off all at once
+                let dropped = (current.bold && !span.style.bold)
+                    || (current.fg.is_some() && span.style.fg.is_none())
+                    || (current.bg.is_some() && span.style.bg.is_none());
+                if span.style.is_plain() || dropped {
+                    result += RESET;
+                }
+                if !span.style.is_plain() {
+                    result += &span.style.prefix();
+                }
+                current = span.style;
+            }
+            result += &span.text;
+        }
+        if !current.is_plain() {
+            result += RESET;
+        }
+        return result;
+    }
+}
+
+impl From<String> for StyledText {
+    fn from(text: String) -> StyledText {
+        return StyledText::from_ansi(&text);
+    }
+}
+
+/// Update a style with the parameters of one SGR sequence. An empty code
+/// resets, as in `\x1b[m`, while one that can't be read is skipped.
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    let codes: Vec<u8> = params.split(';')
+        .filter_map(|code| if code.is_empty() { Some(0) } else { code.parse().ok() })
+        .collect();
+    let mut codes = codes.iter().copied();
+    while let Some(code) = codes.next() {
+        match code {
+            0 => style = Style::default(),
+            1 => style.bold = true,
+            22 => style.bold = false,
+            30..=37 => style.fg = Some(Color::Ansi(code - 30)),
+            90..=97 => style.fg = Some(Color::Ansi(code - 90 + 8)),
+            39 => style.fg = None,
+            38 => style.fg = extended(&mut codes).or(style.fg),
+            40..=47 => style.bg = Some(Color::Ansi(code - 40)),
+            100..=107 => style.bg = Some(Color::Ansi(code - 100 + 8)),
+            49 => style.bg = None,
+            48 => style.bg = extended(&mut codes).or(style.bg),
+            _ => {},
+        }
+    }
+    return style;
+}
+
+/// The color of a `38;5;n` or `38;2;r;g;b` style code, after the 38 or 48.
+fn extended(codes: &mut impl Iterator<Item = u8>) -> Option<Color> {
+    return match codes.next() {
+        Some(5) => codes.next().map(Color::Fixed),
+        Some(2) => {
+            let (r, g, b) = (codes.next(), codes.next(), codes.next());
+            return Some(Color::Rgb(r?, g?, b?));
+        },
+        _ => None,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("red", Some(Color::Ansi(1)))]
+    #[case("bright-blue", Some(Color::Ansi(12)))]
+    #[case("202", Some(Color::Fixed(202)))]
+    #[case("#ff8000", Some(Color::Rgb(255, 128, 0)))]
+    #[case("#ff80", None)]
+    #[case("mauve", None)]
+    fn test_parse(#[case] input: &str, #[case] expected: Option<Color>) {
+        let actual = Color::parse(input);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case(Shell::Bash, "\x1b[31mred\x1b[m", "\x01\x1b[31m\x02red\x01\x1b[m\x02")]
+    #[case(Shell::Zsh, "\x1b[1;94m50%\x1b[m", "%{\x1b[1;94m%}50%%%{\x1b[m%}")]
+    #[case(Shell::Powershell, "\x1b[31m%\x1b[m", "\x1b[31m%\x1b[m")]
+    #[case(Shell::Bash, "plain", "plain")]
+    fn test_shell_wrap(#[case] shell: Shell, #[case] input: &str, #[case] expected: &str) {
+        let actual = shell.wrap(input);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case(0.0, Color::Rgb(0, 0, 0))]
+    #[case(0.5, Color::Rgb(128, 64, 0))]
+    #[case(2.0, Color::Rgb(255, 128, 0))]
+    fn test_mix(#[case] t: f64, #[case] expected: Color) {
+        let actual = Color::Ansi(0).mix(Color::Rgb(255, 128, 0), t);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case(Color::Ansi(1), "red")]
+    #[case(Color::Ansi(12), "bright-blue")]
+    #[case(Color::Fixed(202), "202")]
+    #[case(Color::Rgb(255, 128, 0), "#ff8000")]
+    fn test_display(#[case] color: Color, #[case] expected: &str) {
+        assert_eq!(expected, color.to_string());
+        assert_eq!(Some(color), Color::parse(expected))
+    }
+
+    #[rstest]
+    #[case("plain")]
+    #[case("\x1b[94m/r/crate\x1b[38;5;202m\u{E0A0}\x1b[mmaster(\x1b[31m2\x1b[90m1\x1b[m)")]
+    #[case("\x1b[1;32m#\x1b[m")]
+    #[case("\x1b[30;43m NORMAL \x1b[m")]
+    #[case("\x1b[38;5;0;48;2;255;128;0mx\x1b[m")]
+    fn test_ansi_round_trip(#[case] input: &str) {
+        let actual = StyledText::from_ansi(input).to_ansi();
+        assert_eq!(input, actual)
+    }
+
+    #[rstest]
+    #[case(Format::Plain, "rust 1.80 #1")]
+    #[case(Format::Tmux, "#[fg=colour1]rust#[default] 1.80 ##1")]
+    #[case(Format::Html, "<span style=\"color:#cd0000;\">rust</span> 1.80 #1")]
+    #[case(Format::Ansi, "\x1b[31mrust\x1b[m 1.80 #1")]
+    fn test_render(#[case] format: Format, #[case] expected: &str) {
+        let text = StyledText::new()
+            .push(Style::fg(Color::RED), "rust")
+            .push(Style::default(), " 1.80 #1");
+        let actual = text.render(format);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case(Format::Tmux, "#[fg=colour0,bg=colour3,bold]x#[default]")]
+    #[case(Format::Html, "<span style=\"color:#000000;background-color:#cdcd00;font-weight:bold;\">x</span>")]
+    fn test_render_background(#[case] format: Format, #[case] expected: &str) {
+        let text = StyledText::new().push(Style::fg(Color::Ansi(0)).on(Color::YELLOW).bold(), "x");
+        let actual = text.render(format);
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("31;999;1", Style::fg(Color::RED).bold())]
+    #[case("31;", Style::default())]
+    #[case("42;39", Style::default().on(Color::GREEN))]
+    fn test_apply_sgr(#[case] params: &str, #[case] expected: Style) {
+        let actual = apply_sgr(Style::default(), params);
+        assert_eq!(expected, actual)
+    }
+}
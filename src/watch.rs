@@ -0,0 +1,126 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use super::{git, log};
+
+/// How long a repository has to be quiet before its status is gathered
+/// again, so a checkout or build touching many files costs one refresh.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// When each watched repository's cached status was last brought up to date.
+type Refreshed = Arc<Mutex<HashMap<PathBuf, Instant>>>;
+
+/// Keeps the cached status of repositories up to date as files in them
+/// change, through inotify or FSEvents, so the daemon never waits on
+/// `git status` for a prompt.
+pub struct Watcher {
+    watcher: Mutex<RecommendedWatcher>,
+    watched: Arc<Mutex<HashSet<PathBuf>>>,
+    refreshed: Refreshed,
+}
+
+/// Changes that don't affect the status: objects and logs being written,
+/// and the lock files git makes while it works, including the status
+/// refresh's own.
+fn ignored(path: &Path) -> bool {
+    if path.extension().is_some_and(|extension| extension == "lock") {
+        return true;
+    }
+    return path.ancestors().any(|dir| dir.ends_with(".git/objects") || dir.ends_with(".git/logs"));
+}
+
+/// The watched repository `path` is in, the innermost if they're nested.
+fn owner(watched: &HashSet<PathBuf>, path: &Path) -> Option<PathBuf> {
+    return watched.iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+        .cloned();
+}
+
+/// Collect changed repositories until they've been quiet for `DEBOUNCE`,
+/// then refresh their cached status.
+fn refresh_changes(events: mpsc::Receiver<notify::Result<Event>>, watched: Arc<Mutex<HashSet<PathBuf>>>, refreshed: Refreshed) {
+    let mut pending = HashSet::new();
+    loop {
+        match events.recv_timeout(DEBOUNCE) {
+            Ok(Ok(event)) if !matches!(event.kind, EventKind::Access(_)) => {
+                let watched = watched.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                pending.extend(event.paths.iter().filter(|path| !ignored(path)).filter_map(|path| owner(&watched, path)));
+            },
+            Ok(_) => {},
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                for root in pending.drain() {
+                    log::debug(|| format!("refreshing {} after a change", root.display()));
+                    git::refresh(&root.to_string_lossy());
+                    refreshed.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(root, Instant::now());
+                }
+            },
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+impl Watcher {
+    pub fn new() -> Option<Watcher> {
+        let (sender, events) = mpsc::channel();
+        let watcher = notify::recommended_watcher(sender).ok()?;
+        let watched: Arc<Mutex<HashSet<PathBuf>>> = Arc::default();
+        let refreshed = Refreshed::default();
+        let (thread_watched, thread_refreshed) = (watched.clone(), refreshed.clone());
+        thread::spawn(move || refresh_changes(events, thread_watched, thread_refreshed));
+        return Some(Watcher{watcher: Mutex::new(watcher), watched, refreshed});
+    }
+
+    /// Start watching the repository at `root`, work tree and all, unless
+    /// it already is.
+    pub fn watch(&self, root: &Path) {
+        let mut watched = self.watched.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if watched.contains(root) {
+            return;
+        }
+        let mut watcher = self.watcher.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        match watcher.watch(root, RecursiveMode::Recursive) {
+            Ok(()) => {
+                watched.insert(root.to_path_buf());
+            },
+            Err(error) => log::debug(|| format!("can't watch {}: {error}", root.display())),
+        }
+    }
+
+    /// When the status of the repository at `root` was last refreshed
+    /// after a change, if it has been.
+    pub fn refreshed(&self, root: &Path) -> Option<Instant> {
+        return self.refreshed.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get(root).copied();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("/srv/repo/.git/index.lock", true)]
+    #[case("/srv/repo/.git/objects/ab/cdef", true)]
+    #[case("/srv/repo/.git/logs/HEAD", true)]
+    #[case("/srv/repo/.git/index", false)]
+    #[case("/srv/repo/src/main.rs", false)]
+    fn test_ignored(#[case] path: &str, #[case] expected: bool) {
+        let actual = ignored(Path::new(path));
+        assert_eq!(expected, actual)
+    }
+
+    #[rstest]
+    #[case("/srv/repo/vendor/lib/src/lib.rs", Some("/srv/repo/vendor/lib"))]
+    #[case("/srv/repo/src/main.rs", Some("/srv/repo"))]
+    #[case("/srv/other/main.rs", None)]
+    fn test_owner(#[case] path: &str, #[case] expected: Option<&str>) {
+        let watched = HashSet::from([PathBuf::from("/srv/repo"), PathBuf::from("/srv/repo/vendor/lib")]);
+        let actual = owner(&watched, Path::new(path));
+        assert_eq!(expected.map(PathBuf::from), actual)
+    }
+}